@@ -14,6 +14,9 @@ Source0:        %{name}-%{version}.tar.gz
 BuildRequires:  rust-packaging
 BuildRequires:  cargo
 BuildRequires:  systemd-devel
+# The vendored OpenSSL is compiled during the cargo build.
+BuildRequires:  perl-interpreter
+BuildRequires:  make
 
 # --- Runtime Dependencies ---
 Requires:       iproute
@@ -38,11 +41,15 @@ The core API service is deployed on the guest server and runs via the Canopy Pan
 %install
 # Install the binary into the correct location
 %cargo_install
+install -D -m644 build/%{name}.service %{buildroot}%{_unitdir}/%{name}.service
+install -D -m644 build/%{name}.socket %{buildroot}%{_unitdir}/%{name}.socket
 
 # --- Files Included in the Package ---
 %files
 %license LICENSE
 %{_bindir}/%{name}
+%{_unitdir}/%{name}.service
+%{_unitdir}/%{name}.socket
 
 # --- Changelog ---
 %changelog
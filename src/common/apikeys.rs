@@ -0,0 +1,182 @@
+// src/common/apikeys.rs
+
+use crate::common::{log, setup};
+use crate::middlewares::identity::{Identity, IdentitySource, Scope};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::sync::RwLock;
+
+pub const KEYS_PATH: &str = "/opt/rfs/twig/config/keys.json";
+// Presented as `Bearer twig_<id>_<secret>`; anything else is treated as a node key token.
+pub const PREFIX: &str = "twig_";
+
+const SECRET_SIZE: usize = 32;
+// Keys created before ids grew to this are 4 bytes and keep working.
+const ID_SIZE: usize = 8;
+
+// Why creating or revoking a key did not go through.
+pub enum KeyError {
+    // Bad name or scopes, or the name is taken.
+    Invalid(String),
+    NotFound(String),
+    // keys.json could not be written; nothing changed.
+    Io(String),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    // RFC 3339, UTC.
+    pub created_at: String,
+    // SHA-256 of the secret; the secret itself is only shown on creation.
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    hash: String,
+}
+
+impl ApiKey {
+    pub fn identity(&self) -> Identity {
+        Identity {
+            name: self.name.clone(),
+            source: IdentitySource::ApiKey,
+            scopes: self.scopes.clone(),
+        }
+    }
+
+    // The stored form, without the hash, for listings.
+    pub fn public(&self) -> ApiKey {
+        ApiKey {
+            hash: String::new(),
+            ..self.clone()
+        }
+    }
+}
+
+static KEYS: Lazy<RwLock<Vec<ApiKey>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+// Reads keys.json at startup. A missing file means no named keys yet.
+pub fn load() {
+    let keys = match fs::read_to_string(KEYS_PATH) {
+        Ok(content) => match serde_json::from_str::<Vec<ApiKey>>(&content) {
+            Ok(keys) => keys,
+            Err(e) => {
+                log::log(
+                    log::LogLevel::Error,
+                    &format!("✗ Ignoring {}: {}", KEYS_PATH, e),
+                );
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    };
+    if !keys.is_empty() {
+        log::log(
+            log::LogLevel::Info,
+            &format!("✓ Loaded {} API key(s)", keys.len()),
+        );
+    }
+    *KEYS.write().unwrap() = keys;
+}
+
+pub fn list() -> Vec<ApiKey> {
+    KEYS.read().unwrap().iter().map(ApiKey::public).collect()
+}
+
+// Looks up a `twig_<id>_<secret>` token.
+pub fn authenticate(token: &str) -> Option<ApiKey> {
+    let (id, secret) = token.strip_prefix(PREFIX)?.split_once('_')?;
+    let hash = hash_secret(secret);
+    KEYS.read()
+        .unwrap()
+        .iter()
+        .find(|key| key.id == id && constant_time_eq(key.hash.as_bytes(), hash.as_bytes()))
+        .cloned()
+}
+
+// Creates a key and returns it with the full token, which is not stored anywhere.
+pub fn create(name: &str, scopes: Vec<Scope>) -> Result<(ApiKey, String), KeyError> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 64 {
+        return Err(KeyError::Invalid("name must be 1 to 64 characters".to_string()));
+    }
+    if scopes.is_empty() {
+        return Err(KeyError::Invalid("at least one scope is required".to_string()));
+    }
+
+    let mut keys = KEYS.write().unwrap();
+    if keys.iter().any(|key| key.name == name) {
+        return Err(KeyError::Invalid(format!("a key named '{}' already exists", name)));
+    }
+
+    // Ids are how keys are revoked, so they must be unique.
+    let id = loop {
+        let mut id_bytes = [0u8; ID_SIZE];
+        OsRng.fill_bytes(&mut id_bytes);
+        let id: String = id_bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        if !keys.iter().any(|key| key.id == id || key.name == id) {
+            break id;
+        }
+    };
+    let mut secret_bytes = [0u8; SECRET_SIZE];
+    OsRng.fill_bytes(&mut secret_bytes);
+    let secret = general_purpose::URL_SAFE_NO_PAD.encode(secret_bytes);
+
+    let key = ApiKey {
+        id: id.clone(),
+        name: name.to_string(),
+        scopes,
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        hash: hash_secret(&secret),
+    };
+    let mut updated = keys.clone();
+    updated.push(key.clone());
+    save(&updated).map_err(KeyError::Io)?;
+    *keys = updated;
+
+    log::log(
+        log::LogLevel::Info,
+        &format!("✓ API key '{}' ({}) created", key.name, key.id),
+    );
+    Ok((key.public(), format!("{}{}_{}", PREFIX, id, secret)))
+}
+
+// Removes a key by id or name; requests using it fail from then on.
+pub fn revoke(id_or_name: &str) -> Result<ApiKey, KeyError> {
+    let mut keys = KEYS.write().unwrap();
+    let index = keys
+        .iter()
+        .position(|key| key.id == id_or_name || key.name == id_or_name)
+        .ok_or_else(|| KeyError::NotFound(format!("no key '{}'", id_or_name)))?;
+    let mut updated = keys.clone();
+    let removed = updated.remove(index);
+    save(&updated).map_err(KeyError::Io)?;
+    *keys = updated;
+
+    log::log(
+        log::LogLevel::Warn,
+        &format!("✓ API key '{}' ({}) revoked", removed.name, removed.id),
+    );
+    Ok(removed.public())
+}
+
+fn save(keys: &[ApiKey]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(keys).map_err(|e| e.to_string())?;
+    setup::write_atomic(KEYS_PATH, &json)
+}
+
+fn hash_secret(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,249 @@
+// src/common/audit.rs
+
+use crate::common::{env, log, setup};
+use chrono::{SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+// Keys the entry hashes, so rewriting the log means knowing it. Separate from the
+// node key so that rotating that does not orphan the entries already written.
+pub const KEY_PATH: &str = "/opt/rfs/twig/config/audit.key";
+
+const KEY_SIZE: usize = 32;
+
+// `prev_hash` of the first entry.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// One mutating call. Each entry's hash is an HMAC over its fields and the previous
+// entry's hash, so editing or dropping a line breaks every hash after it.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Entry {
+    pub seq: u64,
+    pub time: String,
+    pub actor: String,
+    pub client_ip: String,
+    pub method: String,
+    pub endpoint: String,
+    pub target: Option<String>,
+    pub outcome: String,
+    pub status: u16,
+    // Status of the Docker Engine call behind the request, if one was made.
+    pub upstream_status: Option<u16>,
+    pub prev_hash: String,
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub hash: String,
+}
+
+// What a handler knows about the call that the audit layer can't see, attached
+// to the response as an extension.
+#[derive(Clone)]
+pub struct Detail {
+    pub target: String,
+    pub upstream_status: Option<u16>,
+}
+
+pub struct Record {
+    pub actor: String,
+    pub client_ip: String,
+    pub method: String,
+    pub endpoint: String,
+    pub status: u16,
+    pub detail: Option<Detail>,
+}
+
+// The last entry written, kept next to the log so that entries cut from the end,
+// which leave the chain itself intact, are noticed.
+#[derive(Serialize, Deserialize)]
+struct Head {
+    seq: u64,
+    hash: String,
+}
+
+// Where the chain currently ends: (last seq, last hash). Read from the file on first use.
+static TAIL: Lazy<Mutex<Option<(u64, String)>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn path() -> PathBuf {
+    let config = env::current();
+    config
+        .audit_path
+        .clone()
+        .unwrap_or_else(|| config.log_dir.join("audit.jsonl"))
+}
+
+// Appends an entry. Failures are logged, never returned: the call itself has already happened.
+pub fn append(record: Record) {
+    let key = match key(true) {
+        Ok(key) => key,
+        Err(e) => {
+            log::log(log::LogLevel::Error, &format!("✗ Audit entry not written, no key: {}", e));
+            return;
+        }
+    };
+    let mut tail = TAIL.lock().unwrap();
+    if tail.is_none() {
+        *tail = Some(read_tail());
+    }
+    let (last_seq, last_hash) = tail.clone().unwrap();
+
+    let outcome = if (200..400).contains(&record.status) { "success" } else { "failure" };
+    let mut entry = Entry {
+        seq: last_seq + 1,
+        time: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        actor: record.actor,
+        client_ip: record.client_ip,
+        method: record.method,
+        endpoint: record.endpoint,
+        target: record.detail.as_ref().map(|d| d.target.clone()),
+        outcome: outcome.to_string(),
+        status: record.status,
+        upstream_status: record.detail.and_then(|d| d.upstream_status),
+        prev_hash: last_hash,
+        hash: String::new(),
+    };
+    entry.hash = hash(&key, &entry);
+
+    let path = path();
+    let line = match serde_json::to_string(&entry) {
+        Ok(line) => line,
+        Err(e) => {
+            log::log(log::LogLevel::Error, &format!("✗ Audit entry not serialized: {}", e));
+            return;
+        }
+    };
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .mode(0o600)
+                .open(&path)
+        })
+        .and_then(|mut file| {
+            file.write_all(format!("{}\n", line).as_bytes())?;
+            file.sync_data()
+        });
+    match written {
+        Ok(()) => {
+            let head = Head { seq: entry.seq, hash: entry.hash.clone() };
+            if let Err(e) = serde_json::to_vec(&head)
+                .map_err(|e| e.to_string())
+                .and_then(|json| setup::write_atomic(&head_path(&path), &json))
+            {
+                log::log(log::LogLevel::Warn, &format!("▲ Audit head not written: {}", e));
+            }
+            *tail = Some((entry.seq, entry.hash));
+        }
+        Err(e) => log::log(
+            log::LogLevel::Error,
+            &format!("✗ Audit log {} not written: {}", path.display(), e),
+        ),
+    }
+}
+
+// All entries in file order, and the seq of the first entry whose chain doesn't check out.
+pub fn read() -> (Vec<Entry>, Option<u64>) {
+    let path = path();
+    let Ok(file) = fs::File::open(&path) else {
+        return (Vec::new(), None);
+    };
+    // Without the key nothing can be checked, so the chain counts as broken from the start.
+    let key = key(false).ok();
+    let mut entries = Vec::new();
+    let mut broken_at = None;
+    let mut prev_hash = GENESIS.to_string();
+    let mut last_seq = 0;
+    for line in BufReader::new(file).lines() {
+        let Ok(line) = line else { break };
+        match serde_json::from_str::<Entry>(&line) {
+            Ok(entry) => {
+                let valid = key.as_ref().is_some_and(|key| hash(key, &entry) == entry.hash);
+                if broken_at.is_none() && (entry.prev_hash != prev_hash || !valid) {
+                    broken_at = Some(entry.seq);
+                }
+                prev_hash = entry.hash.clone();
+                last_seq = entry.seq;
+                entries.push(entry);
+            }
+            Err(_) => {
+                // An unparsable line breaks the chain at the seq it should have had.
+                broken_at.get_or_insert(last_seq + 1);
+            }
+        }
+    }
+
+    if broken_at.is_none()
+        && let Some(head) = fs::read(head_path(&path))
+            .ok()
+            .and_then(|data| serde_json::from_slice::<Head>(&data).ok())
+    {
+        match entries.iter().find(|entry| entry.seq == head.seq) {
+            Some(entry) if entry.hash == head.hash => {}
+            Some(_) => broken_at = Some(head.seq),
+            // Truncated: the first missing entry is where the chain breaks.
+            None => broken_at = Some(last_seq + 1),
+        }
+    }
+    (entries, broken_at)
+}
+
+fn read_tail() -> (u64, String) {
+    let (entries, broken_at) = read();
+    if let Some(seq) = broken_at {
+        log::log(
+            log::LogLevel::Warn,
+            &format!("▲ Audit log chain broken at entry {}", seq),
+        );
+    }
+    entries
+        .last()
+        .map(|entry| (entry.seq, entry.hash.clone()))
+        .unwrap_or((0, GENESIS.to_string()))
+}
+
+fn hash(key: &[u8], entry: &Entry) -> String {
+    let unsigned = Entry {
+        hash: String::new(),
+        ..entry.clone()
+    };
+    let json = serde_json::to_string(&unsigned).unwrap_or_default();
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(key) else {
+        return String::new();
+    };
+    mac.update(json.as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+// Reads the audit key, generating it on first use when `create` is set.
+fn key(create: bool) -> Result<Zeroizing<Vec<u8>>, String> {
+    match fs::read(KEY_PATH) {
+        Ok(key) if key.len() == KEY_SIZE => Ok(Zeroizing::new(key)),
+        Ok(_) => Err(format!("{} is not a {}-byte key", KEY_PATH, KEY_SIZE)),
+        Err(e) if create && e.kind() == std::io::ErrorKind::NotFound => {
+            let mut key = Zeroizing::new(vec![0u8; KEY_SIZE]);
+            OsRng.fill_bytes(&mut key);
+            setup::write_atomic(KEY_PATH, &key)?;
+            log::log(log::LogLevel::Info, &format!("✓ Audit key generated at {}", KEY_PATH));
+            Ok(key)
+        }
+        Err(e) => Err(format!("Cannot read {}: {}", KEY_PATH, e)),
+    }
+}
+
+fn head_path(path: &std::path::Path) -> String {
+    format!("{}.head", path.display())
+}
@@ -0,0 +1,349 @@
+// src/common/bans.rs
+
+use crate::common::audit::{self, Detail, Record};
+use crate::common::{env, log, setup};
+use chrono::{DateTime, SecondsFormat, Utc};
+use dashmap::DashMap;
+use ipnet::IpNet;
+use once_cell::sync::Lazy;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::IpAddr;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::time;
+
+pub const BANS_PATH: &str = "/opt/rfs/twig/config/bans.json";
+
+// Ban durations double with each repeat, up to this.
+const MAX_BAN: Duration = Duration::from_secs(30 * 86400);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Ban {
+    pub id: String,
+    // A single address is stored as /32 or /128.
+    pub network: IpNet,
+    pub reason: String,
+    // "auto" for score-triggered bans, otherwise who added it.
+    pub source: String,
+    // RFC 3339, UTC.
+    pub created_at: String,
+    // RFC 3339, UTC; none for a permanent ban.
+    pub expires_at: Option<String>,
+    #[serde(skip)]
+    until: Option<DateTime<Utc>>,
+}
+
+impl Ban {
+    // Time left on a temporary ban; None for a permanent one.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.until.map(|until| (until - Utc::now()).to_std().unwrap_or_default())
+    }
+
+    fn expired(&self) -> bool {
+        self.until.is_some_and(|until| until <= Utc::now())
+    }
+}
+
+// What an address did to earn points towards a ban.
+#[derive(Clone, Copy, Debug)]
+pub enum Offence {
+    // Fell into a scanner trap, see `traps`.
+    Scanner,
+    // Requested something outside the versioned API.
+    Guard,
+    // Kept going after being rate limited.
+    RateLimit,
+}
+
+impl Offence {
+    fn points(self) -> u32 {
+        match self {
+            Offence::Scanner => 25,
+            Offence::Guard => 10,
+            Offence::RateLimit => 20,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Offence::Scanner => "scanner path",
+            Offence::Guard => "unversioned path",
+            Offence::RateLimit => "rate limit",
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct Score {
+    pub network: IpNet,
+    pub score: u32,
+    // Automatic bans so far; each doubles the next one.
+    pub strikes: u32,
+    #[serde(skip)]
+    last: Option<Instant>,
+    #[serde(skip)]
+    last_strike: Option<Instant>,
+}
+
+impl Score {
+    fn strikes_expired(&self, expiry: Duration) -> bool {
+        self.last_strike.is_none_or(|last| last.elapsed() > expiry)
+    }
+}
+
+// Why lifting a ban did not go through.
+pub enum RemoveError {
+    NotFound(String),
+    // bans.json could not be written; the ban stays.
+    Io(String),
+}
+
+static BANS: Lazy<RwLock<Vec<Ban>>> = Lazy::new(|| RwLock::new(Vec::new()));
+static SCORES: Lazy<DashMap<IpNet, Score>> = Lazy::new(DashMap::new);
+
+// Reads bans.json at startup, dropping bans that expired while the server was down.
+pub fn load() {
+    let bans = match fs::read_to_string(BANS_PATH) {
+        Ok(content) => match serde_json::from_str::<Vec<Ban>>(&content) {
+            Ok(bans) => bans,
+            Err(e) => {
+                log::log(log::LogLevel::Error, &format!("✗ Ignoring {}: {}", BANS_PATH, e));
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    };
+    let bans: Vec<Ban> = bans.into_iter().filter_map(with_expiry).filter(|ban| !ban.expired()).collect();
+    if !bans.is_empty() {
+        log::log(log::LogLevel::Info, &format!("✓ Loaded {} ban(s)", bans.len()));
+    }
+    *BANS.write().unwrap() = bans;
+}
+
+pub fn start_cleanup_task() {
+    tokio::spawn(async {
+        loop {
+            time::sleep(Duration::from_secs(60)).await;
+            let config = env::current();
+            SCORES.retain(|_, score| {
+                score.last.is_some_and(|last| last.elapsed() < config.ban_window)
+                    || !score.strikes_expired(config.ban_strike_expiry)
+            });
+            let _ = tokio::task::spawn_blocking(remove_expired).await;
+        }
+    });
+}
+
+pub fn list() -> Vec<Ban> {
+    BANS.read().unwrap().iter().filter(|ban| !ban.expired()).cloned().collect()
+}
+
+// Addresses currently collecting points, highest first.
+pub fn scores() -> Vec<Score> {
+    let mut scores: Vec<Score> = SCORES.iter().map(|entry| entry.value().clone()).collect();
+    scores.sort_by_key(|score| std::cmp::Reverse(score.score));
+    scores
+}
+
+// The ban covering an address, if any.
+pub fn find(ip: IpAddr) -> Option<Ban> {
+    BANS.read()
+        .unwrap()
+        .iter()
+        .find(|ban| ban.network.contains(&ip) && !ban.expired())
+        .cloned()
+}
+
+// Bans a network, replacing any ban on exactly the same network. No duration means permanent.
+pub fn add(network: IpNet, reason: &str, source: &str, duration: Option<Duration>) -> Result<Ban, String> {
+    let network = network.trunc();
+    let now = Utc::now();
+    let until = duration.map(|d| now + chrono::Duration::from_std(d).unwrap_or_default());
+    let mut id_bytes = [0u8; 4];
+    OsRng.fill_bytes(&mut id_bytes);
+    let ban = Ban {
+        id: id_bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+        network,
+        reason: reason.to_string(),
+        source: source.to_string(),
+        created_at: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+        expires_at: until.map(|until| until.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        until,
+    };
+
+    let mut bans = BANS.write().unwrap();
+    let mut updated: Vec<Ban> = bans.iter().filter(|b| b.network != network).cloned().collect();
+    updated.push(ban.clone());
+    save(&updated)?;
+    *bans = updated;
+
+    // Automatic bans are logged with their score by `auto_ban`.
+    if source != "auto" {
+        let length = duration.map_or_else(|| "permanently".to_string(), |d| format!("for {}s", d.as_secs()));
+        log::log(
+            log::LogLevel::Warn,
+            &format!("✓ {} banned {} by {}: {}", network, length, source, reason),
+        );
+    }
+    Ok(ban)
+}
+
+// Lifts a ban by id or network.
+pub fn remove(id_or_network: &str) -> Result<Ban, RemoveError> {
+    let network = parse_network(id_or_network).ok();
+    let mut bans = BANS.write().unwrap();
+    let index = bans
+        .iter()
+        .position(|ban| ban.id == id_or_network || Some(ban.network) == network)
+        .ok_or_else(|| RemoveError::NotFound(format!("no ban '{}'", id_or_network)))?;
+    let mut updated = bans.clone();
+    let removed = updated.remove(index);
+    save(&updated).map_err(RemoveError::Io)?;
+    *bans = updated;
+    // A lifted ban starts the address over.
+    SCORES.remove(&removed.network);
+
+    log::log(
+        log::LogLevel::Warn,
+        &format!("✓ Ban on {} ({}) lifted", removed.network, removed.id),
+    );
+    Ok(removed)
+}
+
+// An address or CIDR block as written by an admin.
+pub fn parse_network(raw: &str) -> Result<IpNet, String> {
+    let raw = raw.trim();
+    raw.parse::<IpNet>()
+        .or_else(|_| raw.parse::<IpAddr>().map(IpNet::from))
+        .map(|net| net.trunc())
+        .map_err(|_| format!("expected an address or CIDR block, got '{}'", raw))
+}
+
+// Adds an offence to the address's score and bans it once the score reaches
+// ban.threshold. IPv6 addresses are scored per rate_limit.ipv6_prefix block.
+pub fn offence(ip: IpAddr, offence: Offence, detail: &str) {
+    let config = env::current();
+    if !config.ban_enabled
+        || ip.is_loopback()
+        // Banning a proxy would ban everyone behind it.
+        || config.trusted_proxies.iter().any(|net| net.contains(&ip))
+        || find(ip).is_some()
+    {
+        return;
+    }
+    let network = network_of(ip);
+
+    let mut score = SCORES.entry(network).or_insert_with(|| Score {
+        network,
+        score: 0,
+        strikes: 0,
+        last: None,
+        last_strike: None,
+    });
+    if score.last.is_some_and(|last| last.elapsed() > config.ban_window) {
+        score.score = 0;
+    }
+    if score.strikes_expired(config.ban_strike_expiry) {
+        score.strikes = 0;
+    }
+    score.score += offence.points();
+    score.last = Some(Instant::now());
+    log::log(
+        log::LogLevel::Debug,
+        &format!("▪ {} +{} for {} ({}), score {}/{}", network, offence.points(), offence.as_str(), detail, score.score, config.ban_threshold),
+    );
+    if score.score < config.ban_threshold {
+        return;
+    }
+
+    let permanent = config.ban_permanent_after > 0 && score.strikes + 1 >= config.ban_permanent_after;
+    let duration = (!permanent).then(|| {
+        config.ban_duration.saturating_mul(1u32 << score.strikes.min(16)).min(MAX_BAN)
+    });
+    let reason = format!("score {} (last: {} {})", score.score, offence.as_str(), detail);
+    score.score = 0;
+    score.strikes += 1;
+    score.last_strike = Some(Instant::now());
+    drop(score);
+
+    tokio::task::spawn_blocking(move || auto_ban(network, reason, duration));
+}
+
+fn auto_ban(network: IpNet, reason: String, duration: Option<Duration>) {
+    let ban = match add(network, &reason, "auto", duration) {
+        Ok(ban) => ban,
+        Err(e) => {
+            log::log(log::LogLevel::Error, &format!("✗ Ban on {} not saved: {}", network, e));
+            return;
+        }
+    };
+    let length = duration.map_or_else(|| "permanently".to_string(), |d| format!("for {}s", d.as_secs()));
+    log::log(
+        log::LogLevel::Warn,
+        &format!("▲ {} banned {}: {}", network, length, reason),
+    );
+    audit::append(Record {
+        actor: "system".to_string(),
+        client_ip: network.addr().to_string(),
+        method: "EVENT".to_string(),
+        endpoint: "security.ban".to_string(),
+        status: 403,
+        detail: Some(Detail {
+            target: ban.network.to_string(),
+            upstream_status: None,
+        }),
+    });
+}
+
+// What an address is scored and banned as: itself, or its rate_limit.ipv6_prefix block.
+pub fn network_of(ip: IpAddr) -> IpNet {
+    let prefix = match ip {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => env::current().rate_limit_ipv6_prefix as u8,
+    };
+    IpNet::new(ip, prefix).map(|net| net.trunc()).unwrap_or_else(|_| IpNet::from(ip))
+}
+
+fn remove_expired() {
+    let mut bans = BANS.write().unwrap();
+    if !bans.iter().any(Ban::expired) {
+        return;
+    }
+    let (expired, active): (Vec<Ban>, Vec<Ban>) = bans.iter().cloned().partition(Ban::expired);
+    if let Err(e) = save(&active) {
+        log::log(log::LogLevel::Error, &format!("✗ Expired bans not removed: {}", e));
+        return;
+    }
+    *bans = active;
+    for ban in expired {
+        log::log(log::LogLevel::Info, &format!("➜ Ban on {} expired", ban.network));
+    }
+}
+
+// Only a missing expiry makes a ban permanent. One that does not parse is dropped
+// like an expired ban rather than turning a temporary ban into a permanent one.
+fn with_expiry(mut ban: Ban) -> Option<Ban> {
+    let Some(raw) = ban.expires_at.as_deref() else {
+        return Some(ban);
+    };
+    match DateTime::parse_from_rfc3339(raw) {
+        Ok(until) => {
+            ban.until = Some(until.with_timezone(&Utc));
+            Some(ban)
+        }
+        Err(e) => {
+            log::log(
+                log::LogLevel::Warn,
+                &format!("▲ Dropping ban on {} ({}): expires_at '{}' is invalid: {}", ban.network, ban.id, raw, e),
+            );
+            None
+        }
+    }
+}
+
+fn save(bans: &[Ban]) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(bans).map_err(|e| e.to_string())?;
+    setup::write_atomic(BANS_PATH, &json)
+}
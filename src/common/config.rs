@@ -0,0 +1,598 @@
+// src/common/config.rs
+
+use crate::common::env::Config;
+use crate::middlewares::acl::{AclRule, Action};
+use crate::middlewares::client_ip::ProxyHeader;
+use crate::middlewares::clientcert::ClientIdentity;
+use crate::middlewares::identity::Scope;
+use crate::middlewares::router::RateLimitRule;
+use crate::modules::axum::listener;
+use crate::modules::ip::discovery;
+use ipnet::IpNet;
+use std::env;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+use toml::{Table, Value};
+
+pub const DEFAULT_CONFIG_PATH: &str = "/opt/rfs/twig/config/twig.toml";
+
+// Every accepted key, as `section.key`, with the environment variable that overrides it.
+// Keys without a variable can only be set in the file.
+const KEYS: &[(&str, Option<&str>)] = &[
+    ("stage", Some("STAGE")),
+    ("log.level", Some("LOG_LEVEL")),
+    ("log.dir", Some("LOG_DIR")),
+    ("server.listen", Some("LISTEN")),
+    ("server.shutdown_timeout", Some("SHUTDOWN_TIMEOUT")),
+    ("tls.cert", Some("TLS_CERT")),
+    ("tls.key", Some("TLS_KEY")),
+    ("tls.client_ca", Some("TLS_CLIENT_CA")),
+    ("tls.client_required", Some("TLS_CLIENT_REQUIRED")),
+    ("tls.client_identities", None),
+    ("unix_socket.mode", Some("UNIX_SOCKET_MODE")),
+    ("unix_socket.group", Some("UNIX_SOCKET_GROUP")),
+    ("unix_socket.users", Some("UNIX_SOCKET_USERS")),
+    ("cors.canopy_domain", Some("CANOPY_DOMAIN")),
+    ("cors.allowlist", Some("CORS_ALLOWLIST")),
+    ("auth.token_step", Some("TOKEN_STEP")),
+    ("auth.token_window", Some("TOKEN_WINDOW")),
+    ("auth.token_skew_detect", Some("TOKEN_SKEW_DETECT")),
+    ("auth.rotation_grace", Some("ROTATION_GRACE")),
+    ("auth.signature_skew", Some("SIGNATURE_SKEW")),
+    ("lockout.threshold", Some("LOCKOUT_THRESHOLD")),
+    ("lockout.window", Some("LOCKOUT_WINDOW")),
+    ("lockout.ban", Some("LOCKOUT_BAN")),
+    ("lockout.max_backoff", Some("LOCKOUT_MAX_BACKOFF")),
+    ("rate_limit.default", Some("RATE_LIMIT_DEFAULT")),
+    ("rate_limit.rules", None),
+    ("rate_limit.ipv6_prefix", Some("RATE_LIMIT_IPV6_PREFIX")),
+    ("rate_limit.identity_default", Some("RATE_LIMIT_IDENTITY_DEFAULT")),
+    ("rate_limit.identities", None),
+    ("proxy.trusted", Some("TRUSTED_PROXIES")),
+    ("proxy.header", Some("PROXY_HEADER")),
+    ("acl.default", Some("ACL_DEFAULT")),
+    ("acl.rules", None),
+    ("ban.enabled", Some("BAN_ENABLED")),
+    ("ban.threshold", Some("BAN_THRESHOLD")),
+    ("ban.window", Some("BAN_WINDOW")),
+    ("ban.duration", Some("BAN_DURATION")),
+    ("ban.permanent_after", Some("BAN_PERMANENT_AFTER")),
+    ("ban.strike_expiry", Some("BAN_STRIKE_EXPIRY")),
+    ("docker.socket", Some("DOCKER_SOCKET")),
+    ("audit.path", Some("AUDIT_PATH")),
+    ("cache.ip_ttl", Some("IP_CACHE_TTL")),
+    ("cache.geoip_ttl", Some("GEOIP_CACHE_TTL")),
+    ("cache.sysinfo_ttl", Some("SYSINFO_CACHE_TTL")),
+    ("discovery.enabled", Some("DISCOVERY_ENABLED")),
+    ("discovery.stun", Some("DISCOVERY_STUN")),
+    ("discovery.http", Some("DISCOVERY_HTTP")),
+    ("discovery.timeout", Some("DISCOVERY_TIMEOUT")),
+];
+
+// Keys that are only read while the server starts; changing them needs a restart.
+pub const RESTART_KEYS: &[&str] = &[
+    "stage",
+    "server.listen",
+    "tls.cert",
+    "tls.key",
+    "tls.client_ca",
+    "tls.client_required",
+    "unix_socket.mode",
+    "unix_socket.group",
+    "log.dir",
+    "audit.path",
+];
+
+enum Source<'a> {
+    File(&'a Value),
+    Env(&'a str),
+}
+
+// The config file path, overridable with TWIG_CONFIG.
+pub fn path() -> PathBuf {
+    env::var("TWIG_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_CONFIG_PATH))
+}
+
+// Builds the configuration from compiled-in defaults, the config file and then
+// environment variables. Every invalid key or value is reported, not just the first.
+pub fn build() -> Result<Config, Vec<String>> {
+    let mut config = Config::default();
+    let mut errors = Vec::new();
+    let path = path();
+
+    if path.exists() {
+        let origin = path.display().to_string();
+        match fs::read_to_string(&path) {
+            Ok(content) => match content.parse::<Table>() {
+                Ok(table) => {
+                    for (key, value) in flatten(&table) {
+                        if let Err(e) = apply(&mut config, &key, Source::File(value)) {
+                            errors.push(format!("{}: {}: {}", origin, key, e));
+                        }
+                    }
+                }
+                Err(e) => errors.push(format!("{}: {}", origin, e.to_string().trim())),
+            },
+            Err(e) => errors.push(format!("{}: {}", origin, e)),
+        }
+    }
+
+    for (key, var) in KEYS {
+        let Some(var) = var else { continue };
+        // An empty variable counts as unset, so `.env` templates can leave keys blank.
+        if let Ok(raw) = env::var(var)
+            && !raw.trim().is_empty()
+            && let Err(e) = apply(&mut config, key, Source::Env(&raw))
+        {
+            errors.push(format!("{}: {}", var, e));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
+
+// Lists the restart-only keys whose values differ between two configurations.
+pub fn restart_required(old: &Config, new: &Config) -> Vec<&'static str> {
+    let changed = [
+        old.stage != new.stage,
+        old.listen != new.listen,
+        old.tls_cert != new.tls_cert,
+        old.tls_key != new.tls_key,
+        old.tls_client_ca != new.tls_client_ca,
+        old.tls_client_required != new.tls_client_required,
+        old.unix_socket_mode != new.unix_socket_mode,
+        old.unix_socket_group != new.unix_socket_group,
+        old.log_dir != new.log_dir,
+        old.audit_path != new.audit_path,
+    ];
+    RESTART_KEYS
+        .iter()
+        .zip(changed)
+        .filter(|(_, changed)| *changed)
+        .map(|(key, _)| *key)
+        .collect()
+}
+
+// Flattens sections into `section.key` pairs. Unknown sections are kept whole so
+// they are reported once instead of once per nested key.
+fn flatten(table: &Table) -> Vec<(String, &Value)> {
+    let mut pairs = Vec::new();
+    for (name, value) in table {
+        let is_section = KEYS.iter().any(|(key, _)| key.starts_with(&format!("{}.", name)));
+        match value {
+            Value::Table(section) if is_section => {
+                for (key, value) in section {
+                    pairs.push((format!("{}.{}", name, key), value));
+                }
+            }
+            _ => pairs.push((name.clone(), value)),
+        }
+    }
+    pairs
+}
+
+fn apply(config: &mut Config, key: &str, source: Source) -> Result<(), String> {
+    match key {
+        "stage" => config.stage = non_empty(string(&source)?)?,
+        "log.level" => {
+            let level = string(&source)?.to_lowercase();
+            if !["error", "warn", "info", "debug"].contains(&level.as_str()) {
+                return Err(format!("expected one of error, warn, info, debug, got '{}'", level));
+            }
+            config.log_level = level;
+        }
+        "log.dir" => config.log_dir = PathBuf::from(non_empty(string(&source)?)?),
+        "server.listen" => {
+            let listen = list(&source)?;
+            if listen.is_empty() {
+                return Err("at least one listen address is required".to_string());
+            }
+            for entry in &listen {
+                if !entry.starts_with("unix:") {
+                    listener::parse_spec(entry)?;
+                }
+            }
+            config.listen = listen;
+        }
+        "server.shutdown_timeout" => {
+            config.shutdown_timeout = Duration::from_secs(integer(&source, 0, 3600)?)
+        }
+        "tls.cert" => config.tls_cert = Some(non_empty(string(&source)?)?),
+        "tls.key" => config.tls_key = Some(non_empty(string(&source)?)?),
+        "tls.client_ca" => config.tls_client_ca = Some(non_empty(string(&source)?)?),
+        "tls.client_required" => config.tls_client_required = boolean(&source)?,
+        "tls.client_identities" => config.tls_client_identities = client_identities(&source)?,
+        // A TOML integer is the mode itself, written in octal as 0o660; a string, and
+        // UNIX_SOCKET_MODE, holds the octal digits, as in "660".
+        "unix_socket.mode" => {
+            config.unix_socket_mode = match source {
+                Source::File(Value::Integer(n)) => u32::try_from(*n)
+                    .ok()
+                    .filter(|mode| *mode <= 0o777)
+                    .ok_or_else(|| format!("expected a file mode such as 0o660 or \"660\", got {}", n))?,
+                _ => {
+                    let raw = string(&source)?;
+                    u32::from_str_radix(raw.strip_prefix("0o").unwrap_or(&raw), 8)
+                        .ok()
+                        .filter(|mode| *mode <= 0o777)
+                        .ok_or_else(|| format!("expected an octal file mode such as \"660\", got '{}'", raw))?
+                }
+            };
+        }
+        "unix_socket.group" => config.unix_socket_group = Some(non_empty(string(&source)?)?),
+        "unix_socket.users" => config.unix_socket_users = list(&source)?,
+        "cors.canopy_domain" => config.canopy_domain = non_empty(string(&source)?)?,
+        "cors.allowlist" => config.cors_allowlist = list(&source)?,
+        "auth.token_step" => config.token_step = integer(&source, 1, 3600)?,
+        "auth.token_window" => config.token_window = integer(&source, 0, 10)?,
+        "auth.token_skew_detect" => {
+            config.token_skew_detect = Duration::from_secs(integer(&source, 0, 86400)?)
+        }
+        "auth.signature_skew" => {
+            config.signature_skew = Duration::from_secs(integer(&source, 5, 3600)?)
+        }
+        "auth.rotation_grace" => {
+            config.rotation_grace = Duration::from_secs(integer(&source, 0, 30 * 86400)?)
+        }
+        "lockout.threshold" => config.lockout_threshold = integer(&source, 1, 1000)? as u32,
+        "lockout.window" => config.lockout_window = Duration::from_secs(integer(&source, 1, 86400)?),
+        "lockout.ban" => config.lockout_ban = Duration::from_secs(integer(&source, 1, 86400)?),
+        "lockout.max_backoff" => {
+            config.lockout_max_backoff = Duration::from_secs(integer(&source, 0, 3600)?)
+        }
+        "rate_limit.default" => config.rate_limit_default = rule(&source)?,
+        "rate_limit.rules" => config.rate_limit_rules = rules(&source, "path")?,
+        "rate_limit.ipv6_prefix" => config.rate_limit_ipv6_prefix = integer(&source, 32, 128)? as u32,
+        "rate_limit.identity_default" => config.rate_limit_identity_default = Some(rule(&source)?),
+        "rate_limit.identities" => config.rate_limit_identities = rules(&source, "identity")?,
+        "proxy.trusted" => config.trusted_proxies = cidrs(&source)?,
+        "proxy.header" => {
+            config.proxy_header = match string(&source)?.to_lowercase().as_str() {
+                "forwarded" => ProxyHeader::Forwarded,
+                "x-forwarded-for" => ProxyHeader::XForwardedFor,
+                "x-real-ip" => ProxyHeader::XRealIp,
+                other => {
+                    return Err(format!("expected forwarded, x-forwarded-for or x-real-ip, got '{}'", other));
+                }
+            }
+        }
+        "acl.default" => {
+            config.acl_default = match string(&source)?.to_lowercase().as_str() {
+                "allow" => Action::Allow,
+                "deny" => Action::Deny,
+                other => return Err(format!("expected allow or deny, got '{}'", other)),
+            }
+        }
+        "acl.rules" => config.acl_rules = acl_rules(&source)?,
+        "ban.enabled" => config.ban_enabled = boolean(&source)?,
+        "ban.threshold" => config.ban_threshold = integer(&source, 1, 100_000)? as u32,
+        "ban.window" => config.ban_window = Duration::from_secs(integer(&source, 1, 7 * 86400)?),
+        "ban.duration" => config.ban_duration = Duration::from_secs(integer(&source, 1, 30 * 86400)?),
+        "ban.permanent_after" => config.ban_permanent_after = integer(&source, 0, 100)? as u32,
+        "ban.strike_expiry" => {
+            config.ban_strike_expiry = Duration::from_secs(integer(&source, 1, 365 * 86400)?)
+        }
+        "docker.socket" => config.docker_socket = PathBuf::from(non_empty(string(&source)?)?),
+        "audit.path" => config.audit_path = Some(PathBuf::from(non_empty(string(&source)?)?)),
+        "cache.ip_ttl" => config.ip_cache_ttl = Duration::from_secs(integer(&source, 0, 86400)?),
+        "cache.geoip_ttl" => {
+            config.geoip_cache_ttl = Duration::from_secs(integer(&source, 0, 86400)?)
+        }
+        "cache.sysinfo_ttl" => {
+            config.sysinfo_cache_ttl = Duration::from_secs(integer(&source, 0, 86400)?)
+        }
+        "discovery.enabled" => config.discovery_enabled = boolean(&source)?,
+        "discovery.stun" => {
+            let servers = list(&source)?;
+            for server in &servers {
+                let valid = server
+                    .rsplit_once(':')
+                    .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+                if !valid {
+                    return Err(format!("expected host:port, got '{}'", server));
+                }
+            }
+            config.discovery_stun = servers;
+        }
+        "discovery.http" => {
+            let urls = list(&source)?;
+            for url in &urls {
+                discovery::parse_url(url)?;
+            }
+            config.discovery_http = urls;
+        }
+        "discovery.timeout" => {
+            config.discovery_timeout = Duration::from_secs(integer(&source, 1, 60)?)
+        }
+        _ => return Err("unknown key".to_string()),
+    }
+    Ok(())
+}
+
+// --- Value conversion helpers ---
+
+fn string(source: &Source) -> Result<String, String> {
+    match source {
+        Source::File(Value::String(s)) => Ok(s.trim().to_string()),
+        Source::File(other) => Err(format!("expected a string, got {}", other.type_str())),
+        Source::Env(raw) => Ok(raw.trim().to_string()),
+    }
+}
+
+fn non_empty(value: String) -> Result<String, String> {
+    if value.is_empty() {
+        Err("must not be empty".to_string())
+    } else {
+        Ok(value)
+    }
+}
+
+fn boolean(source: &Source) -> Result<bool, String> {
+    match source {
+        Source::File(Value::Boolean(b)) => Ok(*b),
+        Source::File(other) => Err(format!("expected a boolean, got {}", other.type_str())),
+        Source::Env(raw) => match raw.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(true),
+            "false" | "0" | "no" | "off" => Ok(false),
+            other => Err(format!("expected true or false, got '{}'", other)),
+        },
+    }
+}
+
+fn integer(source: &Source, min: u64, max: u64) -> Result<u64, String> {
+    let value = match source {
+        Source::File(Value::Integer(n)) => u64::try_from(*n).ok(),
+        Source::File(other) => return Err(format!("expected an integer, got {}", other.type_str())),
+        Source::Env(raw) => raw.trim().parse::<u64>().ok(),
+    };
+    value
+        .filter(|n| (min..=max).contains(n))
+        .ok_or_else(|| format!("expected an integer between {} and {}", min, max))
+}
+
+// Lists are arrays of strings in the file and comma or whitespace separated in env vars.
+fn list(source: &Source) -> Result<Vec<String>, String> {
+    match source {
+        Source::File(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s.trim().to_string()),
+                other => Err(format!("expected an array of strings, found {}", other.type_str())),
+            })
+            .collect(),
+        Source::File(other) => Err(format!("expected an array of strings, got {}", other.type_str())),
+        Source::Env(raw) => Ok(raw
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect()),
+    }
+}
+
+// A rule is `{ period = 1, limit = 10 }` in the file and `limit/period` (e.g. 10/1) in env vars.
+fn rule(source: &Source) -> Result<RateLimitRule, String> {
+    match source {
+        Source::File(Value::Table(table)) => rule_from_table(table, None).map(|(_, rule)| rule),
+        Source::File(other) => Err(format!("expected a table, got {}", other.type_str())),
+        Source::Env(raw) => {
+            let (limit, period) = raw
+                .trim()
+                .split_once('/')
+                .ok_or_else(|| format!("expected limit/period_secs, got '{}'", raw))?;
+            let limit = limit.parse::<u32>().ok().filter(|n| *n > 0);
+            let period = period.parse::<u64>().ok().filter(|n| *n > 0);
+            match (limit, period) {
+                (Some(limit), Some(period)) => Ok(RateLimitRule {
+                    period: Duration::from_secs(period),
+                    limit,
+                }),
+                _ => Err(format!("expected positive limit/period_secs, got '{}'", raw)),
+            }
+        }
+    }
+}
+
+// CIDR blocks; a bare address counts as a single host.
+fn cidrs(source: &Source) -> Result<Vec<IpNet>, String> {
+    list(source)?
+        .iter()
+        .map(|raw| {
+            raw.parse::<IpNet>()
+                .or_else(|_| raw.parse::<IpAddr>().map(IpNet::from))
+                .map(|net| net.trunc())
+                .map_err(|_| format!("expected an address or CIDR block, got '{}'", raw))
+        })
+        .collect()
+}
+
+// [[acl.rules]] entries: action = "allow" | "deny", networks = [...], optional paths = [...].
+fn acl_rules(source: &Source) -> Result<Vec<AclRule>, String> {
+    let Source::File(Value::Array(items)) = source else {
+        return Err("expected an array of tables".to_string());
+    };
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let Value::Table(table) = item else {
+            errors.push(format!("[{}] expected a table, got {}", index, item.type_str()));
+            continue;
+        };
+        let mut problems = Vec::new();
+        let mut action = None;
+        let mut networks = Vec::new();
+        let mut paths = Vec::new();
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("action", Value::String(s)) if s == "allow" => action = Some(Action::Allow),
+                ("action", Value::String(s)) if s == "deny" => action = Some(Action::Deny),
+                ("action", _) => problems.push("action must be \"allow\" or \"deny\"".to_string()),
+                ("networks", value @ Value::Array(_)) => match cidrs(&Source::File(value)) {
+                    Ok(list) => networks = list,
+                    Err(e) => problems.push(e),
+                },
+                ("networks", _) => problems.push("networks must be an array of addresses or CIDR blocks".to_string()),
+                ("paths", Value::Array(values)) => {
+                    for value in values {
+                        match value.as_str() {
+                            Some(path) if path.starts_with('/') => paths.push(path.to_string()),
+                            _ => problems.push(format!("path {} must be a string starting with '/'", value)),
+                        }
+                    }
+                }
+                ("paths", _) => problems.push("paths must be an array of strings".to_string()),
+                (other, _) => problems.push(format!("unknown key '{}'", other)),
+            }
+        }
+        if action.is_none() && !problems.iter().any(|p| p.starts_with("action")) {
+            problems.push("action is required".to_string());
+        }
+        if networks.is_empty() && !problems.iter().any(|p| p.contains("network") || p.contains("CIDR")) {
+            problems.push("at least one network is required".to_string());
+        }
+        match action {
+            Some(action) if problems.is_empty() => parsed.push(AclRule { action, networks, paths }),
+            _ => errors.push(format!("[{}] {}", index, problems.join(", "))),
+        }
+    }
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+// Rules keyed by `key`: a path for rate_limit.rules, an identity for rate_limit.identities.
+fn rules(source: &Source, key: &str) -> Result<Vec<(String, RateLimitRule)>, String> {
+    let Source::File(Value::Array(items)) = source else {
+        return Err("expected an array of tables".to_string());
+    };
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let result = match item {
+            Value::Table(table) => rule_from_table(table, Some(key)),
+            other => Err(format!("expected a table, got {}", other.type_str())),
+        };
+        match result {
+            Ok((path, rule)) => parsed.push((path.unwrap_or_default(), rule)),
+            Err(e) => errors.push(format!("[{}] {}", index, e)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+// [[tls.client_identities]] entries: subject = "...", scopes = [...], optional name.
+fn client_identities(source: &Source) -> Result<Vec<ClientIdentity>, String> {
+    let Source::File(Value::Array(items)) = source else {
+        return Err("expected an array of tables".to_string());
+    };
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let Value::Table(table) = item else {
+            errors.push(format!("[{}] expected a table, got {}", index, item.type_str()));
+            continue;
+        };
+        let mut problems = Vec::new();
+        let mut subject = None;
+        let mut name = None;
+        let mut scopes = None;
+        for (key, value) in table {
+            match (key.as_str(), value) {
+                ("subject", Value::String(s)) if !s.trim().is_empty() => subject = Some(s.trim().to_string()),
+                ("subject", _) => problems.push("subject must be a non-empty string".to_string()),
+                ("name", Value::String(s)) if !s.trim().is_empty() => name = Some(s.trim().to_string()),
+                ("name", _) => problems.push("name must be a non-empty string".to_string()),
+                ("scopes", Value::Array(values)) => {
+                    let mut list = Vec::new();
+                    for value in values {
+                        match value.as_str().and_then(Scope::parse) {
+                            Some(scope) => list.push(scope),
+                            None => problems.push(format!("unknown scope {}", value)),
+                        }
+                    }
+                    scopes = Some(list);
+                }
+                ("scopes", _) => problems.push("scopes must be an array of strings".to_string()),
+                (other, _) => problems.push(format!("unknown key '{}'", other)),
+            }
+        }
+        if subject.is_none() && !problems.iter().any(|p| p.starts_with("subject")) {
+            problems.push("subject is required".to_string());
+        }
+        if scopes.as_ref().is_none_or(|s| s.is_empty()) && !problems.iter().any(|p| p.contains("scope")) {
+            problems.push("at least one scope is required".to_string());
+        }
+        if problems.is_empty() {
+            parsed.push(ClientIdentity {
+                subject: subject.unwrap_or_default(),
+                name,
+                scopes: scopes.unwrap_or_default(),
+            });
+        } else {
+            errors.push(format!("[{}] {}", index, problems.join(", ")));
+        }
+    }
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+fn rule_from_table(table: &Table, with_key: Option<&str>) -> Result<(Option<String>, RateLimitRule), String> {
+    let mut problems = Vec::new();
+    let mut name = None;
+    let with_path = with_key == Some("path");
+    let with_identity = with_key == Some("identity");
+    let mut period = None;
+    let mut limit = None;
+
+    for (key, value) in table {
+        match (key.as_str(), value) {
+            ("path", Value::String(p)) if with_path && p.starts_with('/') => name = Some(p.clone()),
+            ("path", _) if with_path => problems.push("path must be a string starting with '/'".to_string()),
+            ("identity", Value::String(i)) if with_identity && !i.trim().is_empty() => name = Some(i.trim().to_string()),
+            ("identity", _) if with_identity => {
+                problems.push("identity must be a non-empty string such as 'apikey:hub'".to_string())
+            }
+            ("period", Value::Integer(n)) if *n > 0 => period = Some(Duration::from_secs(*n as u64)),
+            ("period", _) => problems.push("period must be a positive integer (seconds)".to_string()),
+            ("limit", Value::Integer(n)) if *n > 0 && *n <= u32::MAX as i64 => limit = Some(*n as u32),
+            ("limit", _) => problems.push("limit must be a positive integer".to_string()),
+            (other, _) => problems.push(format!("unknown key '{}'", other)),
+        }
+    }
+    if let Some(key) = with_key
+        && name.is_none()
+        && !problems.iter().any(|p| p.starts_with(key))
+    {
+        problems.push(format!("{} is required", key));
+    }
+    match (period, limit) {
+        (Some(period), Some(limit)) if problems.is_empty() => {
+            Ok((name, RateLimitRule { period, limit }))
+        }
+        _ => {
+            if period.is_none() && !problems.iter().any(|p| p.starts_with("period")) {
+                problems.push("period is required".to_string());
+            }
+            if limit.is_none() && !problems.iter().any(|p| p.starts_with("limit")) {
+                problems.push("limit is required".to_string());
+            }
+            Err(problems.join(", "))
+        }
+    }
+}
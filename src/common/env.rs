@@ -1,33 +1,245 @@
 // src/common/env.rs
 
+use crate::common::{config, log, traps};
+use crate::middlewares::acl::{AclRule, Action};
+use crate::middlewares::client_ip::ProxyHeader;
+use crate::middlewares::clientcert::ClientIdentity;
+use crate::middlewares::router::{self, RateLimitRule};
 use dotenvy::dotenv;
-use lazy_static::lazy_static;
-use std::env;
+use ipnet::IpNet;
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
 
+// Settings come from compiled-in defaults, then the config file, then environment
+// variables; see `common::config` for the keys and their variables.
+#[derive(Clone)]
 pub struct Config {
     pub stage: String,
     pub log_level: String,
+    pub log_dir: PathBuf,
     pub canopy_domain: String,
+    pub cors_allowlist: Vec<String>,
+    pub listen: Vec<String>,
+    pub tls_cert: Option<String>,
+    pub tls_key: Option<String>,
+    pub tls_client_ca: Option<String>,
+    pub tls_client_required: bool,
+    pub tls_client_identities: Vec<ClientIdentity>,
+    pub unix_socket_mode: u32,
+    pub unix_socket_group: Option<String>,
+    pub unix_socket_users: Vec<String>,
+    pub shutdown_timeout: Duration,
+    pub token_step: u64,
+    pub token_window: u64,
+    pub token_skew_detect: Duration,
+    pub rotation_grace: Duration,
+    pub signature_skew: Duration,
+    pub lockout_threshold: u32,
+    pub lockout_window: Duration,
+    pub lockout_ban: Duration,
+    pub lockout_max_backoff: Duration,
+    pub rate_limit_default: RateLimitRule,
+    pub rate_limit_rules: Vec<(String, RateLimitRule)>,
+    pub rate_limit_ipv6_prefix: u32,
+    pub trusted_proxies: Vec<IpNet>,
+    pub proxy_header: ProxyHeader,
+    pub acl_default: Action,
+    pub acl_rules: Vec<AclRule>,
+    pub ban_enabled: bool,
+    pub ban_threshold: u32,
+    pub ban_window: Duration,
+    pub ban_duration: Duration,
+    pub ban_permanent_after: u32,
+    pub ban_strike_expiry: Duration,
+    pub rate_limit_identity_default: Option<RateLimitRule>,
+    pub rate_limit_identities: Vec<(String, RateLimitRule)>,
+    pub docker_socket: PathBuf,
+    pub audit_path: Option<PathBuf>,
+    pub ip_cache_ttl: Duration,
+    pub geoip_cache_ttl: Duration,
+    pub sysinfo_cache_ttl: Duration,
+    pub discovery_enabled: bool,
+    pub discovery_stun: Vec<String>,
+    pub discovery_http: Vec<String>,
+    pub discovery_timeout: Duration,
 }
 
-impl Config {
-    fn from_env() -> Self {
-        dotenv().ok();
-        let stage = env::var("STAGE").expect("FATAL: Missing required environment variable: STAGE");
-        let log_level = env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string());
-        let canopy_domain = env::var("CANOPY_DOMAIN").unwrap_or_else(|_| "*".to_string());
+impl Default for Config {
+    fn default() -> Self {
+        let log_dir = if cfg!(windows) {
+            dirs::home_dir()
+                .unwrap_or_default()
+                .join(".canmi/rfs/twig/logs")
+        } else {
+            PathBuf::from("/opt/rfs/twig/logs")
+        };
         Config {
-            stage,
-            log_level,
-            canopy_domain,
+            stage: "production".to_string(),
+            log_level: "info".to_string(),
+            log_dir,
+            canopy_domain: "*".to_string(),
+            // public cloud canopy & canmi's private api
+            cors_allowlist: vec![
+                "rfs.im".to_string(),
+                "*.rfs.im".to_string(),
+                "cloudfaro.com".to_string(),
+                "*.cloudfaro.com".to_string(),
+                "*.canmi.icu".to_string(),
+            ],
+            listen: vec!["0.0.0.0:30721".to_string()],
+            // Without both paths, a self-signed certificate is generated for https:// listeners.
+            tls_cert: None,
+            tls_key: None,
+            // Client certificates are only requested when a CA is configured.
+            tls_client_ca: None,
+            tls_client_required: false,
+            tls_client_identities: Vec::new(),
+            unix_socket_mode: 0o660,
+            unix_socket_group: None,
+            // Root and the user twig runs as are always allowed; others must be listed.
+            unix_socket_users: Vec::new(),
+            shutdown_timeout: Duration::from_secs(10),
+            token_step: 20,
+            token_window: 1,
+            // Tokens this far outside the window are reported as clock skew rather than rejected as wrong.
+            token_skew_detect: Duration::from_secs(3600),
+            // How long the old node key keeps working after a rotation.
+            rotation_grace: Duration::from_secs(3600),
+            // Signed requests older or newer than this are rejected.
+            signature_skew: Duration::from_secs(300),
+            // Failed authentications from one address within the window before it is banned.
+            lockout_threshold: 10,
+            lockout_window: Duration::from_secs(600),
+            // First ban; each further ban of the same address doubles it.
+            lockout_ban: Duration::from_secs(900),
+            // Cap on the delay forced between failed attempts.
+            lockout_max_backoff: Duration::from_secs(60),
+            rate_limit_default: router::default_rule(),
+            rate_limit_rules: router::default_path_rules(),
+            // IPv6 clients are limited per /64; 128 limits each address on its own.
+            rate_limit_ipv6_prefix: 64,
+            // Forwarding headers are ignored unless the peer is listed here.
+            trusted_proxies: Vec::new(),
+            // What nginx sets with $proxy_add_x_forwarded_for.
+            proxy_header: ProxyHeader::XForwardedFor,
+            // What happens to addresses no ACL rule matches.
+            acl_default: Action::Allow,
+            acl_rules: Vec::new(),
+            // Scanner hits score 25, unversioned paths 10, rate limit warnings 20.
+            ban_enabled: true,
+            ban_threshold: 100,
+            // A score is forgotten after this long without offences.
+            ban_window: Duration::from_secs(3600),
+            // First automatic ban; each further one of the same address doubles it.
+            ban_duration: Duration::from_secs(3600),
+            // The third automatic ban is permanent; 0 keeps them all temporary.
+            ban_permanent_after: 3,
+            // An address that stays out of trouble this long after its last automatic ban starts over.
+            ban_strike_expiry: Duration::from_secs(30 * 86400),
+            // Authenticated clients are only limited by address unless configured.
+            rate_limit_identity_default: None,
+            rate_limit_identities: Vec::new(),
+            docker_socket: PathBuf::from("/var/run/docker.sock"),
+            // audit.jsonl in the log directory unless set.
+            audit_path: None,
+            ip_cache_ttl: Duration::from_secs(900),
+            geoip_cache_ttl: Duration::from_secs(900),
+            sysinfo_cache_ttl: Duration::from_secs(900),
+            // Air-gapped nodes set discovery.enabled = false; nothing is contacted then.
+            discovery_enabled: true,
+            discovery_stun: vec![
+                "stun.cloudflare.com:3478".to_string(),
+                "stun.l.google.com:19302".to_string(),
+            ],
+            discovery_http: vec![
+                "https://api.ipify.org".to_string(),
+                "https://api6.ipify.org".to_string(),
+            ],
+            discovery_timeout: Duration::from_secs(3),
         }
     }
 }
 
-lazy_static! {
-    pub static ref CONFIG: Config = Config::from_env();
+// Swapped as a whole on reload; readers keep the snapshot they took.
+static CONFIG: Lazy<RwLock<Arc<Config>>> = Lazy::new(|| RwLock::new(Arc::new(Config::default())));
+
+// Returns the active configuration.
+pub fn current() -> Arc<Config> {
+    CONFIG.read().unwrap().clone()
 }
 
+// Loads the configuration at startup. Logging is not up yet, so problems go to
+// stderr and the process exits after listing all of them.
 pub fn load() {
-    let _ = &CONFIG.stage;
+    dotenv().ok();
+    match config::build() {
+        Ok(config) => install(config),
+        Err(errors) => {
+            eprintln!("✗ Invalid configuration:");
+            for error in &errors {
+                eprintln!("  ➜ {}", error);
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+// Re-reads the config file and environment. An invalid configuration is rejected
+// as a whole and the current one stays active.
+pub fn reload() -> Result<Vec<&'static str>, Vec<String>> {
+    let mut config = config::build()?;
+    let restart = config::restart_required(&current(), &config);
+    // Restart-only settings that are read on every use keep their running values:
+    // a development stage turns off authentication, and the audit chain must go on
+    // in the file it started in (audit.path, or log.dir by default).
+    let running = current();
+    config.stage = running.stage.clone();
+    config.log_dir = running.log_dir.clone();
+    config.audit_path = running.audit_path.clone();
+    install(config);
+    Ok(restart)
+}
+
+// Reloads on SIGHUP, along with the trap rules.
+pub fn spawn_reload_listener() {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::log(log::LogLevel::Warn, &format!("▲ Cannot install SIGHUP handler: {}", e));
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            traps::load();
+            match reload() {
+                Ok(restart) => {
+                    log::log(log::LogLevel::Info, "✓ Configuration reloaded");
+                    for key in restart {
+                        log::log(
+                            log::LogLevel::Warn,
+                            &format!("▲ {} changed, takes effect after restart", key),
+                        );
+                    }
+                }
+                Err(errors) => {
+                    for error in &errors {
+                        log::log(log::LogLevel::Error, &format!("✗ {}", error));
+                    }
+                    log::log(
+                        log::LogLevel::Error,
+                        "✗ Configuration reload rejected, keeping current settings",
+                    );
+                }
+            }
+        }
+    });
+}
+
+fn install(config: Config) {
+    log::set_level(&config.log_level);
+    *CONFIG.write().unwrap() = Arc::new(config);
 }
@@ -6,6 +6,7 @@ use lazy_static::lazy_static;
 use std::fs::{self};
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -16,10 +17,12 @@ use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 lazy_static! {
     static ref LAST_LOG_TIME: Mutex<Option<Instant>> = Mutex::new(None);
     static ref LOG_SENDER: Arc<Mutex<Option<mpsc::Sender<String>>>> = Arc::new(Mutex::new(None));
-    static ref CONFIGURED_LOG_LEVEL: LogLevel = LogLevel::from_str(&env::CONFIG.log_level);
-    static ref LOG_FILE_PATH: Arc<Mutex<Option<PathBuf>>> = Arc::new(Mutex::new(None));
+    static ref LOG_THREAD: Mutex<Option<thread::JoinHandle<()>>> = Mutex::new(None);
 }
 
+// Set from the configuration on load and reload.
+static CONFIGURED_LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub enum LogLevel {
     Error = 3,
@@ -46,9 +49,18 @@ pub fn init() {
     start_file_logger();
 }
 
-// Retrieves the path of the current log file, if available.
-pub fn get_log_path() -> Option<PathBuf> {
-    LOG_FILE_PATH.lock().unwrap().clone()
+// Closes the file logger and waits until its buffer has been written out.
+// Messages logged afterwards only reach the console.
+pub fn shutdown() {
+    LOG_SENDER.lock().unwrap().take();
+    if let Some(handle) = LOG_THREAD.lock().unwrap().take() {
+        let _ = handle.join();
+    }
+}
+
+// Changes the minimum level that gets logged.
+pub fn set_level(level: &str) {
+    CONFIGURED_LOG_LEVEL.store(LogLevel::from_str(level) as u8, Ordering::Relaxed);
 }
 
 // A wrapper around standard println that also logs to the file.
@@ -60,7 +72,7 @@ pub fn println(content: &str) {
 // Logs a formatted message to the console and a clean version to the file.
 pub fn log(level: LogLevel, content: &str) {
     // --- Log Level Filtering ---
-    if (level as u8) < (*CONFIGURED_LOG_LEVEL as u8) {
+    if (level as u8) < CONFIGURED_LOG_LEVEL.load(Ordering::Relaxed) {
         return;
     }
 
@@ -117,15 +129,8 @@ fn start_file_logger() {
     let (tx, rx) = mpsc::channel::<String>();
     *LOG_SENDER.lock().unwrap() = Some(tx);
 
-    thread::spawn(move || {
-        let log_path = match create_log_path() {
-            Ok(path) => {
-                // Store the path for global access
-                *LOG_FILE_PATH.lock().unwrap() = Some(path.clone());
-                Some(path)
-            }
-            Err(_) => None,
-        };
+    let handle = thread::spawn(move || {
+        let log_path = create_log_path().ok();
 
         if log_path.is_none() {
             return; // Failed to create log path, exit thread.
@@ -155,6 +160,7 @@ fn start_file_logger() {
             }
         }
     });
+    *LOG_THREAD.lock().unwrap() = Some(handle);
 }
 
 // Appends all messages in the buffer to the log file.
@@ -172,15 +178,7 @@ fn flush_buffer_to_file(path: &PathBuf, buffer: &mut Vec<String>) {
 // Creates the log directory and returns the full path for the new log file.
 fn create_log_path() -> io::Result<PathBuf> {
     let now = Local::now();
-    let base_dir = if cfg!(windows) {
-        dirs::home_dir().ok_or(io::Error::new(
-            io::ErrorKind::NotFound,
-            "Home directory not found",
-        ))?
-        .join(".canmi/rfs/twig/logs")
-    } else {
-        PathBuf::from("/opt/rfs/twig/logs")
-    };
+    let base_dir = env::current().log_dir.clone();
 
     let dir = base_dir.join(now.format("%Y-%m-%d").to_string());
     fs::create_dir_all(&dir)?;
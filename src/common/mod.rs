@@ -2,5 +2,10 @@
 
 pub mod log;
 pub mod env;
-pub mod sudo;
-pub mod setup;
\ No newline at end of file
+pub mod config;
+pub mod privilege;
+pub mod setup;
+pub mod apikeys;
+pub mod audit;
+pub mod bans;
+pub mod traps;
@@ -0,0 +1,86 @@
+// src/common/privilege.rs
+
+use once_cell::sync::Lazy;
+use std::ffi::CString;
+use std::fmt;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+// Linux capabilities the collectors may need, by their bit number in CapEff.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Capability {
+    NetBindService = 10,
+    NetRaw = 13,
+    SysRawio = 17,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::NetBindService => "CAP_NET_BIND_SERVICE",
+            Capability::NetRaw => "CAP_NET_RAW",
+            Capability::SysRawio => "CAP_SYS_RAWIO",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// The effective capability set never changes after startup, so it's read once.
+static EFFECTIVE: Lazy<u64> = Lazy::new(read_effective);
+
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+// On Linux this is the CapEff bit, so root without the capability (e.g. in a
+// restricted container) is reported correctly. Elsewhere only root qualifies.
+pub fn has_capability(cap: Capability) -> bool {
+    *EFFECTIVE & (1 << cap as u64) != 0
+}
+
+// Whether the current user may read and write a path, e.g. through group
+// membership on the docker socket.
+pub fn can_access(path: &Path) -> bool {
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(c_path.as_ptr(), libc::R_OK | libc::W_OK) == 0 }
+}
+
+// A one-line description of who we run as, e.g. "uid 998 with CAP_NET_RAW".
+pub fn describe() -> String {
+    let all = [Capability::NetBindService, Capability::NetRaw, Capability::SysRawio];
+    let held: Vec<String> = all
+        .into_iter()
+        .filter(|cap| has_capability(*cap))
+        .map(|cap| cap.to_string())
+        .collect();
+    if is_root() && held.len() == all.len() {
+        return "root".to_string();
+    }
+    let uid = unsafe { libc::geteuid() };
+    let who = if uid == 0 { "root".to_string() } else { format!("uid {}", uid) };
+    if held.is_empty() {
+        format!("{} without extra capabilities", who)
+    } else {
+        format!("{} with {}", who, held.join(", "))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_effective() -> u64 {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("CapEff:"))
+                .and_then(|hex| u64::from_str_radix(hex.trim(), 16).ok())
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_effective() -> u64 {
+    if is_root() { u64::MAX } else { 0 }
+}
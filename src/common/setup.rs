@@ -1,38 +1,178 @@
 // src/common/setup.rs
 
 use std::{
-    fs::{self, File},
-    io::{Read, Write},
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    os::unix::fs::{MetadataExt, OpenOptionsExt},
+    io::Write,
     path::Path,
+    sync::Mutex,
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use base64::{engine::general_purpose, Engine as _};
-use chrono::Utc;
+use chrono::{DateTime, SecondsFormat, Utc};
+use once_cell::sync::Lazy;
 use rand::{rngs::OsRng, Rng, RngCore};
 use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::common::{env, log};
 
 const SEED_SIZE: usize = 64;
 const TOKEN_COUNT: usize = 6;
-const PASSWD_PATH: &str = "/opt/rfs/twig/config/passwd";
+const SEEDS_SIZE: usize = SEED_SIZE * TOKEN_COUNT;
+pub const PASSWD_PATH: &str = "/opt/rfs/twig/config/passwd";
+// The key replaced by the last rotation, prefixed with its expiry as big-endian unix seconds.
+pub const PREVIOUS_PATH: &str = "/opt/rfs/twig/config/passwd.previous";
+
+// Seed files: "TWIG", format version, 3 reserved bytes, payload, then a SHA-256
+// of everything before it. Files from before the header are the bare payload.
+const MAGIC: &[u8; 4] = b"TWIG";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_SIZE: usize = 8;
+const CHECKSUM_SIZE: usize = 32;
+const EXPIRY_SIZE: usize = 8;
+
+// Changes to the seed files, e.g. by `twig token rotate`, are picked up within this.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Key material is wiped from memory when dropped.
+type Seeds = Zeroizing<Vec<u8>>;
+
+// Identifies a version of a file on disk; a rename or rewrite changes it.
+type FileStamp = (u64, i64, i64, u64);
+
+#[derive(Default)]
+struct SeedCache {
+    current: Option<Seeds>,
+    // Expiry as unix seconds, and the seeds.
+    previous: Option<(i64, Seeds)>,
+    stamps: (Option<FileStamp>, Option<FileStamp>),
+    checked: Option<Instant>,
+}
+
+static CACHE: Lazy<Mutex<SeedCache>> = Lazy::new(|| Mutex::new(SeedCache::default()));
+
+// Clock skew is looked for at most this many steps either side, whatever
+// auth.token_skew_detect allows at a short step.
+const MAX_SKEW_STEPS: i64 = 720;
+
+// Tokens from outside the accepted window and their offset in steps, for the
+// step, settings and keys they were computed with.
+struct SkewTable {
+    now: i64,
+    step: i64,
+    window: i64,
+    range: i64,
+    keys: Vec<Seeds>,
+    offsets: HashMap<String, i64>,
+}
+
+static SKEW_TABLE: Lazy<Mutex<Option<SkewTable>>> = Lazy::new(|| Mutex::new(None));
+
+// Which key a presented token was derived from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyGeneration {
+    Current,
+    // Replaced by a rotation but still inside its grace period.
+    Previous,
+}
 
+pub struct Rotation {
+    seeds: Seeds,
+    pub previous_valid_until: Option<DateTime<Utc>>,
+}
+
+impl Rotation {
+    // The new node key as handed to clients.
+    pub fn key(&self) -> String {
+        general_purpose::STANDARD.encode(&self.seeds)
+    }
+
+    pub fn reveal(&self) {
+        reveal_key(&self.seeds);
+    }
+}
+
+// Generates the node key on first start. An existing key is checked, and migrated
+// if it predates the versioned format; one that cannot be read stops the server.
 pub fn init_token() {
     if Path::new(PASSWD_PATH).exists() {
+        if let Err(e) = fs::read(PASSWD_PATH)
+            .map_err(|e| e.to_string())
+            .and_then(|data| decode(PASSWD_PATH, &data, SEEDS_SIZE))
+        {
+            log::log(log::LogLevel::Error, &format!("✗ Node key unreadable: {}", e));
+            log::log(
+                log::LogLevel::Error,
+                "✗ Restore the file from a backup or replace the key with `twig token rotate`",
+            );
+            std::process::exit(1);
+        }
+        refresh(true);
         return;
     }
+    let seeds = generate_seeds();
+    save_seed_to_file(&seeds);
+    reveal_key(&seeds);
+}
+
+// Replaces the seeds with new ones. The old key keeps working for `grace` so
+// clients can be updated; a zero grace revokes it at once.
+pub fn rotate_token(grace: Duration) -> Result<Rotation, String> {
+    let old = read_seeds();
+    let seeds = generate_seeds();
 
-    let all_seeds = (0..TOKEN_COUNT)
-        .flat_map(|_| generate_seed())
-        .collect::<Vec<u8>>();
+    // The previous key is written first, so there is no moment where neither works.
+    let previous_valid_until = match old {
+        Some(old) if !grace.is_zero() => {
+            let until = Utc::now() + chrono::Duration::from_std(grace).unwrap_or_default();
+            let mut data = Zeroizing::new(until.timestamp().to_be_bytes().to_vec());
+            data.extend_from_slice(&old);
+            write_atomic(PREVIOUS_PATH, &encode(&data))?;
+            Some(until)
+        }
+        _ => {
+            remove_previous();
+            None
+        }
+    };
+    write_atomic(PASSWD_PATH, &encode(&seeds))?;
+    refresh(true);
 
-    save_seed_to_file(&all_seeds);
+    match previous_valid_until {
+        Some(until) => log::log(
+            log::LogLevel::Warn,
+            &format!("✓ Node key rotated, previous key valid until {}", until.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ),
+        None => log::log(log::LogLevel::Warn, "✓ Node key rotated, previous key revoked"),
+    }
+    Ok(Rotation { seeds, previous_valid_until })
+}
+
+// The token valid right now and the seconds until it rolls over.
+pub fn current_token() -> Result<(String, u64), String> {
+    let buf = read_seeds().ok_or_else(|| format!("Cannot read the node key at {}", PASSWD_PATH))?;
+    let step = env::current().token_step;
+    let now = Utc::now().timestamp();
+    let remaining = step - (now as u64 % step);
+    Ok((token_at(&buf, now / step as i64), remaining))
+}
+
+fn generate_seeds() -> Seeds {
+    Zeroizing::new((0..TOKEN_COUNT).flat_map(|_| generate_seed()).collect())
+}
+
+// Shows a freshly generated key once, on the clipboard on macOS.
+fn reveal_key(all_seeds: &[u8]) {
     thread::sleep(Duration::from_millis(2000));
 
     // === platform specific output ===
     #[cfg(target_os = "macos")]
     {
-        let encoded = general_purpose::STANDARD.encode(&all_seeds);
+        let encoded = general_purpose::STANDARD.encode(all_seeds);
         if let Err(e) = copy_to_clipboard(&encoded) {
             eprintln!("! Failed to copy to clipboard: {}", e);
         }
@@ -40,11 +180,11 @@ pub fn init_token() {
 
     #[cfg(not(target_os = "macos"))]
     {
-        print_seed_base64(&all_seeds);
+        print_seed_base64(all_seeds);
     }
 
     thread::sleep(Duration::from_millis(500));
-    print_seed_ascii(&all_seeds);
+    print_seed_ascii(all_seeds);
     thread::sleep(Duration::from_millis(500));
 
     #[cfg(target_os = "macos")]
@@ -62,36 +202,233 @@ pub fn init_token() {
     thread::sleep(Duration::from_millis(3000));
 }
 
-pub fn compute_token_windows() -> [String; 3] {
-    let mut buf = [0u8; SEED_SIZE * TOKEN_COUNT];
-    File::open(PASSWD_PATH)
-        .expect("Token seed file not found")
-        .read_exact(&mut buf)
-        .expect("Failed to read token seeds");
-
-    let now = Utc::now().timestamp() / 20;
-    let times = [now - 1, now, now + 1];
-    let mut tokens = vec![];
-
-    for &timestamp in &times {
-        let mut result = vec![];
-        for i in 0..TOKEN_COUNT {
-            let seed = &buf[i * SEED_SIZE..(i + 1) * SEED_SIZE];
-            let mut hasher = Sha256::new();
-            hasher.update(seed);
-            hasher.update(timestamp.to_be_bytes());
-            let hash = hasher.finalize();
-            let number = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]) % 1_000_000;
-            result.push(format!("{:06}", number));
+// Tokens accepted right now for the current key; none if the key cannot be read.
+pub fn compute_token_windows() -> Vec<String> {
+    read_seeds().map(|buf| windows(&buf)).unwrap_or_default()
+}
+
+// Which key, if any, a bearer token was derived from.
+pub fn match_token(token: &str) -> Option<KeyGeneration> {
+    if compute_token_windows().iter().any(|valid| valid == token) {
+        return Some(KeyGeneration::Current);
+    }
+    let previous = read_previous()?;
+    windows(&previous)
+        .iter()
+        .any(|valid| valid == token)
+        .then_some(KeyGeneration::Previous)
+}
+
+// For a token that matched no key: how far off the client clock is, in seconds,
+// if the token is from a valid key but a step outside the accepted window.
+// The tokens are computed once per step and shared by every request in it.
+pub fn token_skew(token: &str) -> Option<i64> {
+    let config = env::current();
+    let step = config.token_step as i64;
+    let window = config.token_window as i64;
+    let range = (config.token_skew_detect.as_secs() as i64 / step).min(MAX_SKEW_STEPS);
+    let now = Utc::now().timestamp() / step;
+    let keys: Vec<Seeds> = read_seeds().into_iter().chain(read_previous()).collect();
+
+    let mut table = SKEW_TABLE.lock().unwrap();
+    let stale = table.as_ref().is_none_or(|table| {
+        table.now != now || table.step != step || table.window != window || table.range != range || table.keys != keys
+    });
+    if stale {
+        // Farthest steps first, so the nearest one that explains a token overwrites
+        // the others and the reported skew is the smallest.
+        let mut offsets = HashMap::new();
+        for offset in (window + 1..=range).rev().flat_map(|offset| [-offset, offset]) {
+            for key in &keys {
+                offsets.insert(token_at(key, now + offset), offset);
+            }
         }
-        tokens.push(base64::engine::general_purpose::STANDARD.encode(result.join("").as_bytes()));
+        *table = Some(SkewTable { now, step, window, range, keys, offsets });
     }
+    table.as_ref()?.offsets.get(token).map(|offset| offset * step)
+}
 
-    tokens.try_into().unwrap()
+// When the previous key stops being accepted, if one is still in its grace period.
+pub fn previous_valid_until() -> Option<DateTime<Utc>> {
+    refresh(false);
+    let expiry = CACHE.lock().unwrap().previous.as_ref()?.0;
+    DateTime::from_timestamp(expiry, 0).filter(|until| *until > Utc::now())
+}
+
+// Format version of the node key file: 0 for the bare legacy layout, which is
+// migrated on the next start.
+pub fn seed_file_version() -> Result<u8, String> {
+    let data = fs::read(PASSWD_PATH).map_err(|e| format!("{}: {}", PASSWD_PATH, e))?;
+    decode(PASSWD_PATH, &data, SEEDS_SIZE)?;
+    Ok(if data.starts_with(MAGIC) { data[4] } else { 0 })
+}
+
+// HMAC keys for signed requests: sha256("twig-request-signing" || seeds) of the
+// current key and, during its grace period, the previous one.
+pub fn signing_keys() -> Vec<(KeyGeneration, [u8; 32])> {
+    let derive = |seeds: &[u8]| -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"twig-request-signing");
+        hasher.update(seeds);
+        hasher.finalize().into()
+    };
+    let mut keys = Vec::new();
+    if let Some(seeds) = read_seeds() {
+        keys.push((KeyGeneration::Current, derive(&seeds)));
+    }
+    if let Some(seeds) = read_previous() {
+        keys.push((KeyGeneration::Previous, derive(&seeds)));
+    }
+    keys
 }
 
 /* --- Internal helpers --- */
 
+fn read_seeds() -> Option<Seeds> {
+    refresh(false);
+    CACHE.lock().unwrap().current.clone()
+}
+
+// The previous seeds while their grace period lasts; an expired file is removed.
+fn read_previous() -> Option<Seeds> {
+    refresh(false);
+    let mut cache = CACHE.lock().unwrap();
+    let (expiry, seeds) = cache.previous.as_ref()?;
+    if *expiry > Utc::now().timestamp() {
+        return Some(seeds.clone());
+    }
+    cache.previous = None;
+    if remove_previous() {
+        log::log(log::LogLevel::Info, "➜ Grace period over, previous node key removed");
+    }
+    None
+}
+
+// Reloads the seed files into the cache if they changed on disk. Between
+// rechecks nothing is read, so authenticating a request costs no file access.
+fn refresh(force: bool) {
+    let mut cache = CACHE.lock().unwrap();
+    if !force && cache.checked.is_some_and(|at| at.elapsed() < RECHECK_INTERVAL) {
+        return;
+    }
+    cache.checked = Some(Instant::now());
+
+    let stamps = (stamp(PASSWD_PATH), stamp(PREVIOUS_PATH));
+    if !force && stamps == cache.stamps {
+        return;
+    }
+    if force || stamps.0 != cache.stamps.0 {
+        cache.current = load(PASSWD_PATH, SEEDS_SIZE);
+    }
+    if force || stamps.1 != cache.stamps.1 {
+        cache.previous = load(PREVIOUS_PATH, EXPIRY_SIZE + SEEDS_SIZE).and_then(|data| {
+            let expiry = i64::from_be_bytes(data[..EXPIRY_SIZE].try_into().ok()?);
+            Some((expiry, Zeroizing::new(data[EXPIRY_SIZE..].to_vec())))
+        });
+    }
+    // Taken again, as a migration rewrites the file.
+    cache.stamps = (stamp(PASSWD_PATH), stamp(PREVIOUS_PATH));
+}
+
+fn stamp(path: &str) -> Option<FileStamp> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.ino(), meta.mtime(), meta.mtime_nsec(), meta.len()))
+}
+
+// Reads and checks a seed file, rewriting it in the current format if it is a legacy one.
+fn load(path: &str, payload_size: usize) -> Option<Seeds> {
+    let data = match fs::read(path) {
+        Ok(data) => Zeroizing::new(data),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if path == PASSWD_PATH {
+                log::log(log::LogLevel::Error, &format!("✗ Node key {} missing, tokens are rejected", path));
+            }
+            return None;
+        }
+        Err(e) => {
+            log::log(log::LogLevel::Error, &format!("✗ Cannot read {}: {}", path, e));
+            return None;
+        }
+    };
+    let payload = match decode(path, &data, payload_size) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::log(log::LogLevel::Error, &format!("✗ {}, its key is not accepted", e));
+            return None;
+        }
+    };
+    if !data.starts_with(MAGIC) {
+        match write_atomic(path, &encode(&payload)) {
+            Ok(()) => log::log(
+                log::LogLevel::Info,
+                &format!("✓ Migrated {} to seed format v{}", path, FORMAT_VERSION),
+            ),
+            Err(e) => log::log(log::LogLevel::Warn, &format!("▲ {} not migrated: {}", path, e)),
+        }
+    }
+    Some(payload)
+}
+
+fn encode(payload: &[u8]) -> Seeds {
+    let mut data = Zeroizing::new(Vec::with_capacity(HEADER_SIZE + payload.len() + CHECKSUM_SIZE));
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&[FORMAT_VERSION, 0, 0, 0]);
+    data.extend_from_slice(payload);
+    let checksum = Sha256::digest(&data);
+    data.extend_from_slice(&checksum);
+    data
+}
+
+// The payload of a seed file, in either format.
+fn decode(path: &str, data: &[u8], payload_size: usize) -> Result<Seeds, String> {
+    if !data.starts_with(MAGIC) {
+        // A bare payload of the right size is the legacy format; anything else is damage.
+        return if data.len() == payload_size {
+            Ok(Zeroizing::new(data.to_vec()))
+        } else {
+            Err(format!("{} is {} bytes, not a seed file", path, data.len()))
+        };
+    }
+    if data.len() != HEADER_SIZE + payload_size + CHECKSUM_SIZE {
+        return Err(format!("{} is truncated or padded ({} bytes)", path, data.len()));
+    }
+    if data[4] != FORMAT_VERSION {
+        return Err(format!("{} has unsupported format version {}", path, data[4]));
+    }
+    let (body, checksum) = data.split_at(data.len() - CHECKSUM_SIZE);
+    if Sha256::digest(body).as_slice() != checksum {
+        return Err(format!("{} failed its checksum", path));
+    }
+    Ok(Zeroizing::new(body[HEADER_SIZE..].to_vec()))
+}
+
+fn remove_previous() -> bool {
+    fs::remove_file(PREVIOUS_PATH).is_ok()
+}
+
+fn windows(buf: &[u8]) -> Vec<String> {
+    let config = env::current();
+    let now = Utc::now().timestamp() / config.token_step as i64;
+    let window = config.token_window as i64;
+    (now - window..=now + window)
+        .map(|timestamp| token_at(buf, timestamp))
+        .collect()
+}
+
+fn token_at(buf: &[u8], timestamp: i64) -> String {
+    let mut result = vec![];
+    for i in 0..TOKEN_COUNT {
+        let seed = &buf[i * SEED_SIZE..(i + 1) * SEED_SIZE];
+        let mut hasher = Sha256::new();
+        hasher.update(seed);
+        hasher.update(timestamp.to_be_bytes());
+        let hash = hasher.finalize();
+        let number = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]) % 1_000_000;
+        result.push(format!("{:06}", number));
+    }
+    general_purpose::STANDARD.encode(result.join("").as_bytes())
+}
+
 fn generate_seed() -> [u8; SEED_SIZE] {
     let mut seed = [0u8; SEED_SIZE];
     OsRng.fill_bytes(&mut seed);
@@ -99,12 +436,48 @@ fn generate_seed() -> [u8; SEED_SIZE] {
 }
 
 fn save_seed_to_file(data: &[u8]) {
-    if let Some(dir) = Path::new(PASSWD_PATH).parent() {
-        fs::create_dir_all(dir).expect("! Failed to create config directory");
+    write_atomic(PASSWD_PATH, &encode(data)).expect("! Failed to write token seeds");
+    refresh(true);
+}
+
+// Writes next to the target and renames, so a running server never reads a partial
+// file. Used for every credential file, hence owner-only permissions.
+pub fn write_atomic(path: &str, data: &[u8]) -> Result<(), String> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
     }
+    let tmp_path = format!("{}.tmp", path);
+    // A leftover temp file would keep its old permissions, so start from a fresh one.
+    let _ = fs::remove_file(&tmp_path);
+    let owner = owner_for(path);
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            if let Some((uid, gid)) = owner {
+                std::os::unix::fs::fchown(&file, Some(uid), Some(gid))?;
+            }
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp_path, path))
+        .map_err(|e| format!("Cannot write {}: {}", path, e))
+}
 
-    let mut file = File::create(PASSWD_PATH).expect("! Failed to create token file");
-    file.write_all(data).expect("! Failed to write token seeds");
+// When root writes a file for a service running as another user, e.g. `twig token
+// rotate` under sudo, the file keeps the owner of the one it replaces, or takes the
+// directory's. Other users can only create files they own themselves.
+fn owner_for(path: &str) -> Option<(u32, u32)> {
+    if unsafe { libc::geteuid() } != 0 {
+        return None;
+    }
+    let path = Path::new(path);
+    fs::metadata(path)
+        .ok()
+        .or_else(|| fs::metadata(path.parent()?).ok())
+        .map(|meta| (meta.uid(), meta.gid()))
 }
 
 #[cfg(not(target_os = "macos"))]
@@ -120,7 +493,7 @@ fn print_seed_ascii(data: &[u8]) {
             .iter()
             .map(|b| {
                 let hex = format!("{:02X}", b);
-                let c0 = if rng.gen_bool(0.3) { '0' } else { hex.chars().nth(0).unwrap() };
+                let c0 = if rng.gen_bool(0.3) { '0' } else { hex.chars().next().unwrap() };
                 let c1 = if rng.gen_bool(0.3) { '0' } else { hex.chars().nth(1).unwrap() };
                 format!("{}{} ", c0, c1)
             })
@@ -152,4 +525,63 @@ fn copy_to_clipboard(text: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut clipboard = Clipboard::new()?;
     clipboard.set_text(text.to_owned())?;
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD_SIZE: usize = 16;
+
+    fn payload() -> Vec<u8> {
+        (0..PAYLOAD_SIZE as u8).collect()
+    }
+
+    #[test]
+    fn encoded_files_decode_to_their_payload() {
+        let data = encode(&payload());
+        assert_eq!(data.len(), HEADER_SIZE + PAYLOAD_SIZE + CHECKSUM_SIZE);
+        assert!(data.starts_with(MAGIC));
+        assert_eq!(decode("seeds", &data, PAYLOAD_SIZE).unwrap().as_slice(), payload().as_slice());
+    }
+
+    #[test]
+    fn bare_payloads_are_the_legacy_format() {
+        assert_eq!(decode("seeds", &payload(), PAYLOAD_SIZE).unwrap().as_slice(), payload().as_slice());
+        let err = decode("seeds", &payload()[1..], PAYLOAD_SIZE).err().unwrap();
+        assert!(err.contains("not a seed file"), "{}", err);
+    }
+
+    #[test]
+    fn damaged_files_are_rejected() {
+        let data = encode(&payload());
+
+        let mut flipped = data.to_vec();
+        flipped[HEADER_SIZE] ^= 1;
+        let err = decode("seeds", &flipped, PAYLOAD_SIZE).err().unwrap();
+        assert!(err.contains("checksum"), "{}", err);
+
+        let err = decode("seeds", &data[..data.len() - 1], PAYLOAD_SIZE).err().unwrap();
+        assert!(err.contains("truncated or padded"), "{}", err);
+
+        let mut newer = data.to_vec();
+        newer[4] = FORMAT_VERSION + 1;
+        let err = decode("seeds", &newer, PAYLOAD_SIZE).err().unwrap();
+        assert!(err.contains("format version"), "{}", err);
+    }
+
+    #[test]
+    fn tokens_depend_on_every_seed_and_the_step() {
+        let seeds = vec![7u8; SEEDS_SIZE];
+        let token = token_at(&seeds, 1000);
+        assert_eq!(token, token_at(&seeds, 1000));
+        assert_ne!(token, token_at(&seeds, 1001));
+        let decoded = general_purpose::STANDARD.decode(&token).unwrap();
+        assert_eq!(decoded.len(), TOKEN_COUNT * 6);
+        assert!(decoded.iter().all(u8::is_ascii_digit));
+        for seed in 0..TOKEN_COUNT {
+            let mut changed = seeds.clone();
+            changed[seed * SEED_SIZE] ^= 1;
+            assert_ne!(token, token_at(&changed, 1000));
+        }
+    }
+}
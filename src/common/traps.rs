@@ -0,0 +1,580 @@
+// src/common/traps.rs
+
+use crate::common::{bans, log};
+use axum::http::StatusCode;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use dashmap::DashMap;
+use ipnet::IpNet;
+use once_cell::sync::Lazy;
+use rand::seq::SliceRandom;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::time;
+
+pub const TRAPS_PATH: &str = "/opt/rfs/twig/config/traps.toml";
+
+// Per-address and per-day counts older than this are forgotten.
+const RETENTION_DAYS: i64 = 30;
+// Addresses listed by `stats`, most hits first.
+const TOP_ADDRESSES: usize = 100;
+
+// Built-in rules, used while no traps.toml exists.
+const RESP_418_PATHS: &[&str] = &[
+    "/wp-login.php",
+    "/wp-admin",
+    "/wp-admin/setup-config.php",
+    "/wordpress/",
+    "/wordpress/wp-admin/setup-config.php",
+    "/wp-content/",
+    "/wp-includes/",
+    "/wp-json/",
+    "/xmlrpc.php",
+    "/wp-config.php",
+    "/wp-config.php.bak",
+    "/wp-config.php.old",
+    "/wp-config.php.save",
+    "/wp-config-sample.php",
+    "/wp-cron.php",
+    "/wp-mail.php",
+    "/wp-trackback.php",
+    "/readme.html",
+    "/license.txt",
+    "/wp-activate.php",
+    "/wp-comments-post.php",
+    "/wp-links-opml.php",
+    "/wp-load.php",
+    "/wp-settings.php",
+    "/wp-signup.php",
+    "/wp-blog-header.php",
+    "/plugins/",
+    "/themes/",
+    "/uploads/",
+    "/phpinfo.php",
+    "/phpmyadmin/",
+];
+
+const RESP_403_PATHS: &[&str] = &[
+    "/admin",
+    "/admin/",
+    "/admin/login",
+    "/admin.php",
+    "/admin.html",
+    "/administrator",
+    "/administrator/",
+    "/admin-login",
+    "/login",
+    "/logon",
+    "/login.php",
+    "/login.html",
+    "/register",
+    "/signup",
+    "/dashboard",
+    "/.env",
+    "/.git/config",
+    "/.git",
+    "/.svn",
+    "/.htaccess",
+    "/.idea",
+    "/.vscode",
+    "/.gitignore",
+    "/@vite/env",
+    "/actuator/env",
+    "/server",
+    "/.vscode/sftp.json",
+    "/debug/default/view",
+    "/ecp/Current/exporttool/microsoft.exchange.ediscovery.exporttool.application",
+    "/server-status",
+    "/login.action",
+    "/_all_dbs",
+    "/.DS_Store",
+    "/.git/config",
+    "/s/934313e2131313e2534323e2237313/_/;/META-INF/maven/com.atlassian.jira/jira-webapp-dist/pom.properties",
+    "/config.json",
+    "/telescope/requests",
+    "/info.php",
+];
+
+const RESP_400_PATHS: &[&str] = &[
+    "/config",
+    "/config.php",
+    "/conf",
+    "/database",
+    "/database_backup",
+    "/backup",
+    "/backup.zip",
+    "/api",
+    "/api/login",
+    "/api/v1",
+    "/api/v1/login",
+    "/api/user",
+    "/api/users",
+    "/api/admin",
+    "/api/auth",
+    "/rest",
+    "/rest/login",
+    "/private",
+    "/secure",
+    "/.well-known/security.txt",
+    "/.well-known/change-password",
+    "/.well-known/apple-app-site-association",
+    "/server-status",
+    "/status",
+    "/server-info",
+    "/error",
+    "/errors",
+    "/403",
+    "/404",
+    "/500",
+    "/401",
+];
+
+const TAUNTS: &[&str] = &[
+    "My server is more secure than your script is clever. Try again, maybe after learning to code.",
+    "Congratulations, you've found the 'waste your time' endpoint.",
+    "Your automated scanner is bad and you should feel bad.",
+    "You probe like a script kiddie with broken fingers.",
+    "Try hacking something your own size, champ.",
+    "Wow. Such scan. Very bot. Much blocked.",
+    "If stupidity were a crime, your IP would be in jail.",
+    "You call that an exploit? My grandma could write better malware.",
+    "Your requests are like your skills — rejected.",
+    "I don’t speak bot. Try English next time.",
+    "Keep poking. Maybe you'll find a vulnerability in your own ego.",
+    "The only thing you’ve penetrated is the rate limit.",
+    "Access denied. You're not even worth logging.",
+    "My firewall does more thinking than your entire script.",
+    "Bot detected. Intelligence not detected.",
+    "404: Your skills not found.",
+    "Error: Brain not initialized.",
+    "Try again in your next life.",
+    "Scanning? You're just embarrassing yourself.",
+    "I've seen toddlers write better attack scripts.",
+    "The only thing you're exploiting is your own incompetence.",
+    "Even my 404 page is smarter than your crawler.",
+    "AI called — it wants you to stop.",
+];
+
+const TAUNTS_418: &[&str] = &[
+    "This isn't WordPress, it's worse — it's Rust.",
+    "Looking for WordPress? You must be lost. This is a real server.",
+    "Did you really think you'd find a wp-login.php here? How quaint.",
+    "Is that a vulnerability scanner or are you just happy to see my 418 response?",
+    "Crawling /wp-content? You must be new here.",
+    "418: I'm a teapot, and you're a fool.",
+    "418: We serve real humans here.",
+    "418: Teapot protocol engaged. No coffee for you.",
+    "418: Brew yourself some skills first.",
+    "418: Hot water, no mercy.",
+    "418: Attack rejected. Tea is sacred.",
+];
+
+// One [[trap]] in traps.toml:
+//   name = "wordpress"
+//   prefix = ["/wp-admin"]        # path starts with
+//   exact = ["/xmlrpc.php"]       # path is exactly
+//   glob = ["/**/*.php"]          # * stays within a segment, ** crosses them, ? is one character
+//   regex = ["(?i)^/phpmyadmin"]  # unanchored unless written with ^ or $
+//   status = 418                  # default 403
+//   body = ["Go away."]           # a message, or a list to pick one from at random
+//   drop = true                   # close the connection without answering
+// Rules are tried in file order and the first match answers.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTrap {
+    name: String,
+    #[serde(default)]
+    prefix: Vec<String>,
+    #[serde(default)]
+    exact: Vec<String>,
+    #[serde(default)]
+    glob: Vec<String>,
+    #[serde(default)]
+    regex: Vec<String>,
+    status: Option<u16>,
+    body: Option<RawBody>,
+    #[serde(default)]
+    drop: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawBody {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawFile {
+    #[serde(default)]
+    trap: Vec<RawTrap>,
+}
+
+pub struct Trap {
+    pub name: String,
+    pub status: StatusCode,
+    pub drop: bool,
+    bodies: Vec<String>,
+    prefixes: Vec<String>,
+    exact: Vec<String>,
+    // Globs are compiled to regexes alongside the regex patterns.
+    patterns: Vec<Regex>,
+}
+
+impl Trap {
+    fn matches(&self, path: &str) -> bool {
+        self.prefixes.iter().any(|p| path.starts_with(p.as_str()))
+            || self.exact.iter().any(|p| p == path)
+            || self.patterns.iter().any(|re| re.is_match(path))
+    }
+
+    // One of the configured bodies, or the status's reason phrase.
+    pub fn body(&self) -> String {
+        self.bodies
+            .choose(&mut rand::thread_rng())
+            .cloned()
+            .unwrap_or_else(|| self.status.canonical_reason().unwrap_or("Go away.").to_string())
+    }
+}
+
+struct Rules {
+    // traps.toml, or "built-in".
+    source: String,
+    traps: Vec<Arc<Trap>>,
+}
+
+// Counted per network as bans are, so an IPv6 scanner rotating through its
+// prefix is one entry rather than one per address.
+#[derive(Serialize, Clone)]
+pub struct AddressHits {
+    pub network: IpNet,
+    pub hits: u64,
+    pub last_trap: String,
+    // RFC 3339, UTC.
+    pub last_seen: String,
+    #[serde(skip)]
+    last: DateTime<Utc>,
+}
+
+static RULES: Lazy<RwLock<Arc<Rules>>> = Lazy::new(|| {
+    RwLock::new(Arc::new(Rules {
+        source: "built-in".to_string(),
+        traps: builtin(),
+    }))
+});
+// Counts since startup, keyed by trap name.
+static TRAP_HITS: Lazy<DashMap<String, u64>> = Lazy::new(DashMap::new);
+static ADDRESS_HITS: Lazy<DashMap<IpNet, AddressHits>> = Lazy::new(DashMap::new);
+static DAY_HITS: Lazy<DashMap<NaiveDate, u64>> = Lazy::new(DashMap::new);
+
+// Reads traps.toml at startup and on SIGHUP. Without the file the built-in rules
+// apply; a file that doesn't parse is reported and the current rules stay.
+pub fn load() {
+    let content = match fs::read_to_string(TRAPS_PATH) {
+        Ok(content) => content,
+        Err(_) => {
+            *RULES.write().unwrap() = Arc::new(Rules {
+                source: "built-in".to_string(),
+                traps: builtin(),
+            });
+            return;
+        }
+    };
+    match parse(&content) {
+        Ok(traps) => {
+            log::log(
+                log::LogLevel::Info,
+                &format!("✓ Loaded {} trap rule(s) from {}", traps.len(), TRAPS_PATH),
+            );
+            *RULES.write().unwrap() = Arc::new(Rules {
+                source: TRAPS_PATH.to_string(),
+                traps,
+            });
+        }
+        Err(e) => log::log(
+            log::LogLevel::Error,
+            &format!("✗ Ignoring {}, keeping current trap rules: {}", TRAPS_PATH, e),
+        ),
+    }
+}
+
+pub fn start_cleanup_task() {
+    tokio::spawn(async {
+        loop {
+            time::sleep(Duration::from_secs(3600)).await;
+            let cutoff = Utc::now() - chrono::Duration::days(RETENTION_DAYS);
+            ADDRESS_HITS.retain(|_, hits| hits.last > cutoff);
+            DAY_HITS.retain(|day, _| *day > cutoff.date_naive());
+        }
+    });
+}
+
+// The first trap the path falls into.
+pub fn matching(path: &str) -> Option<Arc<Trap>> {
+    let rules = RULES.read().unwrap().clone();
+    rules.traps.iter().find(|trap| trap.matches(path)).cloned()
+}
+
+pub fn hit(trap: &Trap, ip: Option<IpAddr>) {
+    let now = Utc::now();
+    *TRAP_HITS.entry(trap.name.clone()).or_insert(0) += 1;
+    *DAY_HITS.entry(now.date_naive()).or_insert(0) += 1;
+    let Some(ip) = ip else { return };
+    let network = bans::network_of(ip);
+    let mut hits = ADDRESS_HITS.entry(network).or_insert_with(|| AddressHits {
+        network,
+        hits: 0,
+        last_trap: String::new(),
+        last_seen: String::new(),
+        last: now,
+    });
+    hits.hits += 1;
+    hits.last_trap = trap.name.clone();
+    hits.last_seen = now.to_rfc3339_opts(SecondsFormat::Secs, true);
+    hits.last = now;
+}
+
+// Hit counts per rule, per address and per day (UTC).
+pub fn stats() -> serde_json::Value {
+    let rules = RULES.read().unwrap().clone();
+    let traps: Vec<serde_json::Value> = rules
+        .traps
+        .iter()
+        .map(|trap| {
+            serde_json::json!({
+                "name": trap.name,
+                "status": trap.status.as_u16(),
+                "drop": trap.drop,
+                "hits": TRAP_HITS.get(&trap.name).map_or(0, |hits| *hits),
+            })
+        })
+        .collect();
+    let mut addresses: Vec<AddressHits> = ADDRESS_HITS.iter().map(|entry| entry.value().clone()).collect();
+    addresses.sort_by_key(|hits| std::cmp::Reverse(hits.hits));
+    let total_addresses = addresses.len();
+    addresses.truncate(TOP_ADDRESSES);
+    let mut days: Vec<(NaiveDate, u64)> = DAY_HITS.iter().map(|entry| (*entry.key(), *entry.value())).collect();
+    days.sort();
+    let days: Vec<serde_json::Value> = days
+        .into_iter()
+        .map(|(day, hits)| serde_json::json!({ "date": day.to_string(), "hits": hits }))
+        .collect();
+    serde_json::json!({
+        "source": rules.source,
+        "traps": traps,
+        "addresses": addresses,
+        "total_addresses": total_addresses,
+        "days": days,
+    })
+}
+
+fn parse(content: &str) -> Result<Vec<Arc<Trap>>, String> {
+    let file: RawFile = toml::from_str(content).map_err(|e| e.message().to_string())?;
+    let mut traps: Vec<Arc<Trap>> = Vec::new();
+    let mut errors = Vec::new();
+    for (index, raw) in file.trap.into_iter().enumerate() {
+        let label = format!("[{}] {}", index, raw.name);
+        if traps.iter().any(|trap| trap.name == raw.name) {
+            errors.push(format!("{}: duplicate name", label));
+            continue;
+        }
+        match compile(raw) {
+            Ok(trap) => traps.push(Arc::new(trap)),
+            Err(e) => errors.push(format!("{}: {}", label, e)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(traps)
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+fn compile(raw: RawTrap) -> Result<Trap, String> {
+    if raw.name.trim().is_empty() {
+        return Err("name is required".to_string());
+    }
+    if raw.prefix.is_empty() && raw.exact.is_empty() && raw.glob.is_empty() && raw.regex.is_empty() {
+        return Err("needs at least one prefix, exact, glob or regex pattern".to_string());
+    }
+    let status = match raw.status {
+        Some(code) => StatusCode::from_u16(code)
+            .ok()
+            .filter(|status| status.is_client_error() || status.is_server_error())
+            .ok_or_else(|| format!("status {} is not a 4xx or 5xx code", code))?,
+        None => StatusCode::FORBIDDEN,
+    };
+    let patterns = raw
+        .glob
+        .iter()
+        .map(|glob| glob_to_regex(glob))
+        .chain(raw.regex)
+        .map(|pattern| Regex::new(&pattern).map_err(|e| format!("pattern '{}': {}", pattern, e)))
+        .collect::<Result<Vec<Regex>, String>>()?;
+    Ok(Trap {
+        name: raw.name,
+        status,
+        drop: raw.drop,
+        bodies: match raw.body {
+            Some(RawBody::One(body)) => vec![body],
+            Some(RawBody::Many(bodies)) => bodies,
+            None => Vec::new(),
+        },
+        prefixes: raw.prefix,
+        exact: raw.exact,
+        patterns,
+    })
+}
+
+// Anchored at both ends; `**/` also matches no directory at all.
+fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    out.push_str("(?:.*/)?");
+                } else {
+                    out.push_str(".*");
+                }
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            c => out.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    out.push('$');
+    out
+}
+
+fn builtin() -> Vec<Arc<Trap>> {
+    let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    let trap = |name: &str, status, bodies: Vec<String>, prefixes, exact| {
+        Arc::new(Trap {
+            name: name.to_string(),
+            status,
+            drop: false,
+            bodies,
+            prefixes,
+            exact,
+            patterns: Vec::new(),
+        })
+    };
+    // The plain reason phrase stays among the taunts now and then.
+    let mut teapot = strings(TAUNTS_418);
+    teapot.push("I'm a teapot".to_string());
+    let mut denied = strings(TAUNTS);
+    denied.push("Access denied".to_string());
+    vec![
+        trap("wordpress", StatusCode::IM_A_TEAPOT, teapot, strings(RESP_418_PATHS), Vec::new()),
+        trap("admin-probes", StatusCode::FORBIDDEN, denied, strings(RESP_403_PATHS), Vec::new()),
+        trap("common-probes", StatusCode::BAD_REQUEST, strings(&["Bad request"]), Vec::new(), strings(RESP_400_PATHS)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(pattern: &str, path: &str) -> bool {
+        Regex::new(&glob_to_regex(pattern)).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn single_star_stays_within_a_segment() {
+        assert!(glob("/wp-*", "/wp-admin"));
+        assert!(glob("/wp-*", "/wp-"));
+        assert!(!glob("/wp-*", "/wp-admin/setup.php"));
+        assert!(!glob("/wp-*", "/x/wp-admin"));
+    }
+
+    #[test]
+    fn double_star_crosses_segments() {
+        assert!(glob("/**/*.php", "/index.php"));
+        assert!(glob("/**/*.php", "/a/b/c.php"));
+        assert!(!glob("/**/*.php", "/a/b/c.phps"));
+        assert!(glob("/backup/**", "/backup/db/dump.sql"));
+    }
+
+    #[test]
+    fn question_mark_is_one_character() {
+        assert!(glob("/file?.txt", "/file1.txt"));
+        assert!(!glob("/file?.txt", "/file12.txt"));
+        assert!(!glob("/file?.txt", "/file/.txt"));
+    }
+
+    #[test]
+    fn other_characters_are_literal() {
+        assert!(glob("/a.b+(c)", "/a.b+(c)"));
+        assert!(!glob("/a.b", "/axb"));
+        assert!(!glob("/a", "/a/"));
+    }
+
+    #[test]
+    fn rules_match_in_file_order() {
+        let traps = parse(
+            r#"
+            [[trap]]
+            name = "php"
+            glob = ["/**/*.php"]
+            status = 418
+
+            [[trap]]
+            name = "admin"
+            prefix = ["/admin"]
+            exact = ["/login"]
+            regex = ["(?i)^/phpmyadmin"]
+            drop = true
+            "#,
+        )
+        .unwrap();
+        let first = |path: &str| traps.iter().find(|trap| trap.matches(path)).map(|trap| trap.name.as_str());
+        assert_eq!(first("/admin/index.php"), Some("php"));
+        assert_eq!(first("/admin/panel"), Some("admin"));
+        assert_eq!(first("/PhpMyAdmin/"), Some("admin"));
+        assert_eq!(first("/login/"), None);
+        assert_eq!(traps[0].status, StatusCode::IM_A_TEAPOT);
+        assert_eq!(traps[1].status, StatusCode::FORBIDDEN);
+        assert!(traps[1].drop);
+    }
+
+    #[test]
+    fn invalid_rules_are_all_reported() {
+        let err = parse(
+            r#"
+            [[trap]]
+            name = "ok"
+            exact = ["/a"]
+
+            [[trap]]
+            name = "ok"
+            exact = ["/b"]
+
+            [[trap]]
+            name = "empty"
+
+            [[trap]]
+            name = "status"
+            exact = ["/c"]
+            status = 200
+
+            [[trap]]
+            name = "regex"
+            regex = ["("]
+            "#,
+        )
+        .err()
+        .unwrap();
+        assert!(err.contains("[1] ok: duplicate name"), "{}", err);
+        assert!(err.contains("[2] empty: needs at least one"), "{}", err);
+        assert!(err.contains("[3] status: status 200"), "{}", err);
+        assert!(err.contains("[4] regex: pattern '('"), "{}", err);
+    }
+}
@@ -1,10 +1,11 @@
 // src/core/bootstrap.rs
 
-use crate::common::{env, log};
-use crate::middlewares::rate_limiting;
+use crate::common::{apikeys, bans, env, log, traps};
+use crate::middlewares::{lockout, rate_limiting, signature};
 use crate::modules;
 use crate::core::requirement::run_dependency_check;
 use crate::common::setup::init_token;
+use crate::core::{collector, shutdown};
 use chrono::Local;
 use sysinfo::{Disks, System};
 
@@ -14,7 +15,8 @@ pub async fn init() {
     let sys = System::new_all();
 
     // --- Gather all required information first ---
-    let stage_raw = &env::CONFIG.stage;
+    let config = env::current();
+    let stage_raw = &config.stage;
     let stage = match stage_raw.to_lowercase().as_str() {
         "dev" | "development" => "Development".to_string(),
         // Any other value will default to Production.
@@ -95,11 +97,26 @@ pub async fn init() {
     log::println(&format!("    ✓ {}", fid));
     log::println("");
 
+    modules::app::health::init();
     init_token();
+    apikeys::load();
+    bans::load();
+    traps::load();
     log::log(log::LogLevel::Info, "➜ Starting...");
 
     // --- Start Services ---
     run_dependency_check();
+    collector::log_status();
+    shutdown::listen();
+    env::spawn_reload_listener();
     rate_limiting::start_cleanup_task();
+    signature::start_cleanup_task();
+    lockout::start_cleanup_task();
+    bans::start_cleanup_task();
+    traps::start_cleanup_task();
     modules::axum::core::start().await;
+
+    if shutdown::is_shutting_down() {
+        log::log(log::LogLevel::Info, "✓ Shutdown complete");
+    }
 }
@@ -0,0 +1,317 @@
+// src/core/cli.rs
+
+use crate::common::{audit, config, env, log, privilege, setup};
+use crate::core::collector;
+use crate::core::requirement;
+use chrono::SecondsFormat;
+use serde_json::json;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: twig [COMMAND]
+
+Commands:
+  serve                 Start the server (default)
+  token show-current    Print the token that is valid right now
+  token rotate [--yes] [--grace SECS] [--export FILE]
+                        Replace the node key; the old one keeps working for the
+                        grace period (auth.rotation_grace). With --export the new
+                        key is written to FILE (mode 600) instead of the terminal
+  audit verify          Check the hash chain of the audit log
+  config check          Validate the config file and environment overrides
+  doctor                Check privileges, collectors, config, key file and dependencies
+  version [--json]      Print version information
+  help                  Print this message";
+
+pub enum Command {
+    Serve,
+    TokenShowCurrent,
+    TokenRotate { yes: bool, grace: Option<Duration>, export: Option<PathBuf> },
+    AuditVerify,
+    ConfigCheck,
+    Doctor,
+    Version { json: bool },
+    Help,
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] | ["serve"] => Ok(Command::Serve),
+        ["token", "show-current"] => Ok(Command::TokenShowCurrent),
+        ["token", "rotate", options @ ..] => parse_rotate(options),
+        ["audit", "verify"] => Ok(Command::AuditVerify),
+        ["config", "check"] => Ok(Command::ConfigCheck),
+        ["doctor"] => Ok(Command::Doctor),
+        ["version"] | ["--version" | "-V"] => Ok(Command::Version { json: false }),
+        ["version", "--json"] => Ok(Command::Version { json: true }),
+        ["help"] | ["--help" | "-h"] => Ok(Command::Help),
+        ["token" | "audit" | "config", ..] => Err(format!("Unknown arguments: {}", args.join(" "))),
+        [other, ..] => Err(format!("Unknown command '{}'", other)),
+    }
+}
+
+fn parse_rotate(options: &[&str]) -> Result<Command, String> {
+    let mut yes = false;
+    let mut grace = None;
+    let mut export = None;
+    let mut rest = options.iter();
+    while let Some(option) = rest.next() {
+        match *option {
+            "--yes" | "-y" => yes = true,
+            "--grace" => {
+                let secs = rest
+                    .next()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .ok_or("--grace expects a number of seconds")?;
+                grace = Some(Duration::from_secs(secs));
+            }
+            "--export" => {
+                let path = rest.next().ok_or("--export expects a file path")?;
+                export = Some(PathBuf::from(path));
+            }
+            other => return Err(format!("Unknown option '{}' for token rotate", other)),
+        }
+    }
+    Ok(Command::TokenRotate { yes, grace, export })
+}
+
+// Runs a maintenance command and returns the process exit code.
+// `serve` is handled by main since it owns the runtime.
+pub fn run(command: Command) -> i32 {
+    match command {
+        Command::Serve => 0,
+        Command::TokenShowCurrent => token_show_current(),
+        Command::TokenRotate { yes, grace, export } => token_rotate(yes, grace, export),
+        Command::AuditVerify => audit_verify(),
+        Command::ConfigCheck => config_check(),
+        Command::Doctor => doctor(),
+        Command::Version { json } => version(json),
+        Command::Help => {
+            println!("{}", USAGE);
+            0
+        }
+    }
+}
+
+fn token_show_current() -> i32 {
+    env::load();
+    match setup::current_token() {
+        Ok((token, remaining)) => {
+            println!("{}", token);
+            eprintln!("➜ Valid for another {}s", remaining);
+            if let Some(until) = setup::previous_valid_until() {
+                eprintln!("➜ Previous node key accepted until {}", until.to_rfc3339_opts(SecondsFormat::Secs, true));
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("✗ {}", e);
+            1
+        }
+    }
+}
+
+fn token_rotate(yes: bool, grace: Option<Duration>, export: Option<PathBuf>) -> i32 {
+    env::load();
+    let dir = Path::new(setup::PASSWD_PATH).parent().unwrap_or(Path::new("/"));
+    if !privilege::can_access(dir) {
+        eprintln!("✗ Cannot write to {}; run as root or the service user", dir.display());
+        return 1;
+    }
+    let grace = grace.unwrap_or(env::current().rotation_grace);
+    if !yes {
+        if grace.is_zero() {
+            eprint!("▲ Clients using the current node key will be rejected at once. Rotate? [y/N] ");
+        } else {
+            eprint!("▲ The current node key stops working in {}s. Rotate? [y/N] ", grace.as_secs());
+        }
+        let _ = io::stderr().flush();
+        let mut answer = String::new();
+        let _ = io::stdin().lock().read_line(&mut answer);
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            eprintln!("✗ Aborted");
+            return 1;
+        }
+    }
+
+    // Refuse an existing export target before anything is rotated.
+    let export_file = match &export {
+        Some(path) => match OpenOptions::new().write(true).create_new(true).mode(0o600).open(path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("✗ Cannot create {}: {}", path.display(), e);
+                return 1;
+            }
+        },
+        None => None,
+    };
+
+    // So the rotation is recorded in the service log, not only on this terminal.
+    log::init();
+    let result = setup::rotate_token(grace);
+    log::shutdown();
+    let rotation = match result {
+        Ok(rotation) => rotation,
+        Err(e) => {
+            eprintln!("✗ {}", e);
+            return 1;
+        }
+    };
+
+    match (export_file, &export) {
+        (Some(mut file), Some(path)) => {
+            if let Err(e) = writeln!(file, "{}", rotation.key()) {
+                eprintln!("✗ Rotated, but writing {} failed: {}", path.display(), e);
+                return 1;
+            }
+            eprintln!("✓ New node key written to {}", path.display());
+        }
+        _ => rotation.reveal(),
+    }
+    0
+}
+
+fn audit_verify() -> i32 {
+    env::load();
+    let path = audit::path();
+    let (entries, broken_at) = audit::read();
+    match broken_at {
+        None => {
+            println!("✓ {}: {} entries, chain intact", path.display(), entries.len());
+            0
+        }
+        Some(seq) => {
+            println!("✗ {}: chain broken at entry {} of {}", path.display(), seq, entries.len());
+            1
+        }
+    }
+}
+
+fn config_check() -> i32 {
+    dotenvy::dotenv().ok();
+    let path = config::path();
+    match config::build() {
+        Ok(_) => {
+            if path.exists() {
+                println!("✓ {} is valid", path.display());
+            } else {
+                println!("✓ No config file at {}, defaults and environment are valid", path.display());
+            }
+            0
+        }
+        Err(errors) => {
+            println!("✗ Invalid configuration:");
+            for error in &errors {
+                println!("  ➜ {}", error);
+            }
+            1
+        }
+    }
+}
+
+// Reports everything that would stop the server from starting or working fully.
+fn doctor() -> i32 {
+    dotenvy::dotenv().ok();
+    let mut failures = 0;
+    let mut check = |ok: bool, message: String| {
+        if ok {
+            println!("✓ {}", message);
+        } else {
+            println!("✗ {}", message);
+            failures += 1;
+        }
+    };
+
+    println!("➜ Running as {}", privilege::describe());
+
+    let config = match config::build() {
+        Ok(config) => {
+            check(true, format!("Configuration valid ({})", config::path().display()));
+            config
+        }
+        Err(errors) => {
+            check(false, "Configuration invalid".to_string());
+            for error in &errors {
+                println!("  ➜ {}", error);
+            }
+            env::Config::default()
+        }
+    };
+
+    let passwd = Path::new(setup::PASSWD_PATH);
+    match fs::metadata(passwd) {
+        Ok(meta) => {
+            match setup::seed_file_version() {
+                Ok(0) => println!("▲ Node key at {} uses the legacy format, migrated on next start", passwd.display()),
+                Ok(version) => check(true, format!("Node key present at {} (format v{})", passwd.display(), version)),
+                Err(e) => check(false, format!("Node key unreadable: {}", e)),
+            }
+            if meta.permissions().mode() & 0o077 != 0 {
+                println!("▲ {} is readable by other users (mode {:o})", passwd.display(), meta.permissions().mode() & 0o777);
+            }
+        }
+        Err(_) => println!("▲ No node key yet, one is generated on first start"),
+    }
+
+    // Collectors only degrade, so missing commands or capabilities are warnings.
+    let report = requirement::check_dependencies();
+    if !report.found.is_empty() {
+        println!("✓ Dependencies: {}", report.found.join(", "));
+    }
+    for collector in collector::COLLECTORS {
+        let missing = collector.missing();
+        if missing.is_empty() {
+            println!("✓ Collector {}", collector.name);
+        } else {
+            println!("▲ Collector {} degraded: missing {}", collector.name, collector::describe(&missing));
+        }
+    }
+    if !report.missing.is_empty()
+        && let Some(command) = requirement::install_command(&report)
+    {
+        println!("  ➜ {}", command);
+    }
+
+    match fs::create_dir_all(&config.log_dir) {
+        Ok(_) if privilege::can_access(&config.log_dir) => {
+            check(true, format!("Log directory {}", config.log_dir.display()))
+        }
+        Ok(_) => check(false, format!("Log directory {} is not writable", config.log_dir.display())),
+        Err(e) => check(false, format!("Log directory {}: {}", config.log_dir.display(), e)),
+    }
+
+    if failures == 0 { 0 } else { 1 }
+}
+
+fn version(as_json: bool) -> i32 {
+    let features: Vec<&str> = [("tls", cfg!(feature = "tls"))]
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect();
+
+    if as_json {
+        let info = json!({
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "features": features,
+        });
+        println!("{}", info);
+    } else {
+        println!(
+            "{} {} ({} {})",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        );
+    }
+    0
+}
@@ -0,0 +1,330 @@
+// src/core/collector.rs
+
+use crate::common::env;
+use crate::common::log;
+use crate::common::privilege::{self, Capability};
+use crate::core::requirement;
+use serde::Serialize;
+
+// Something a collector needs from the host beyond what every user has.
+#[derive(Clone, Copy)]
+pub enum Requirement {
+    // An external command that must be on PATH.
+    Command(&'static str),
+    Capability(Capability),
+    // Read/write access to the configured docker socket, usually via the docker group.
+    DockerSocket,
+    // Outbound lookups, switched off with `discovery.enabled = false` on air-gapped nodes.
+    Discovery,
+}
+
+// A requirement that is not met, as reported in 503 bodies and the dependency report.
+#[derive(Serialize, Clone, Debug)]
+pub struct Missing {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+impl Requirement {
+    fn is_met(&self) -> bool {
+        match self {
+            Requirement::Command(cmd) => requirement::is_installed(cmd),
+            Requirement::Capability(cap) => privilege::has_capability(*cap),
+            Requirement::DockerSocket => privilege::can_access(&env::current().docker_socket),
+            Requirement::Discovery => env::current().discovery_enabled,
+        }
+    }
+
+    fn to_missing(self) -> Missing {
+        match self {
+            Requirement::Command(cmd) => Missing {
+                kind: "command",
+                name: cmd.to_string(),
+            },
+            Requirement::Capability(cap) => Missing {
+                kind: "capability",
+                name: cap.to_string(),
+            },
+            Requirement::DockerSocket => Missing {
+                kind: "access",
+                name: env::current().docker_socket.display().to_string(),
+            },
+            Requirement::Discovery => Missing {
+                kind: "config",
+                name: "discovery.enabled".to_string(),
+            },
+        }
+    }
+}
+
+pub struct Collector {
+    pub name: &'static str,
+    // Route prefixes answered with 503 while the collector is unavailable.
+    // Empty for collectors that only enrich another endpoint.
+    pub routes: &'static [&'static str],
+    pub requires: &'static [Requirement],
+    // Where the data comes from, as reported by /v1/capabilities.
+    pub source: &'static str,
+}
+
+#[cfg(target_os = "linux")]
+pub const COLLECTORS: &[Collector] = &[
+    Collector {
+        name: "cpu",
+        routes: &["/v1/monitor/cpu"],
+        requires: &[],
+        source: "procfs",
+    },
+    // Tells VMs apart so their meaningless host frequency is not reported.
+    Collector {
+        name: "cpu-frequency",
+        routes: &[],
+        requires: &[Requirement::Command("systemd-detect-virt")],
+        source: "sysfs",
+    },
+    // The actual interface (intel-rapl, amd-hwmon, arm-iio) is probed per request.
+    Collector {
+        name: "cpu-power",
+        routes: &["/v1/monitor/cpu/power"],
+        requires: &[],
+        source: "sysfs",
+    },
+    Collector {
+        name: "memory",
+        routes: &["/v1/monitor/memory"],
+        requires: &[],
+        source: "procfs",
+    },
+    Collector {
+        name: "network",
+        routes: &["/v1/monitor/network"],
+        requires: &[],
+        source: "procfs",
+    },
+    Collector {
+        name: "storage",
+        routes: &["/v1/monitor/storage"],
+        requires: &[Requirement::Command("lsblk")],
+        source: "lsblk",
+    },
+    // Per-disk I/O rates inside the storage response.
+    Collector {
+        name: "storage-io",
+        routes: &[],
+        requires: &[Requirement::Command("iostat")],
+        source: "iostat",
+    },
+    Collector {
+        name: "system",
+        routes: &["/v1/system/information"],
+        requires: &[],
+        source: "sysinfo",
+    },
+    Collector {
+        name: "ipconfig",
+        routes: &["/v1/system/ipconfig"],
+        requires: &[Requirement::Command("ip")],
+        source: "iproute2",
+    },
+    Collector {
+        name: "public-ip",
+        routes: &["/v1/ip", "/v2/ip"],
+        requires: &[Requirement::Discovery],
+        source: "stun",
+    },
+    Collector {
+        name: "ram-spec",
+        routes: &["/v1/spec/ram"],
+        requires: &[
+            Requirement::Command("dmidecode"),
+            Requirement::Capability(Capability::SysRawio),
+        ],
+        source: "dmidecode",
+    },
+    Collector {
+        name: "docker",
+        routes: &["/v1/containers"],
+        requires: &[Requirement::DockerSocket],
+        source: "docker-engine",
+    },
+];
+
+#[cfg(target_os = "macos")]
+pub const COLLECTORS: &[Collector] = &[
+    Collector {
+        name: "cpu",
+        routes: &["/v1/monitor/cpu"],
+        requires: &[],
+        source: "sysinfo",
+    },
+    Collector {
+        name: "cpu-frequency",
+        routes: &[],
+        requires: &[Requirement::Command("fastfetch")],
+        source: "fastfetch",
+    },
+    Collector {
+        name: "cpu-power",
+        routes: &["/v1/monitor/cpu/power"],
+        requires: &[Requirement::Command("macmon")],
+        source: "macmon",
+    },
+    Collector {
+        name: "memory",
+        routes: &["/v1/monitor/memory"],
+        requires: &[Requirement::Command("macmon")],
+        source: "macmon",
+    },
+    Collector {
+        name: "network",
+        routes: &["/v1/monitor/network"],
+        requires: &[Requirement::Command("netstat")],
+        source: "netstat",
+    },
+    // Per-process traffic from bandwhich needs packet capture; totals work without it.
+    Collector {
+        name: "network-processes",
+        routes: &[],
+        requires: &[
+            Requirement::Command("bandwhich"),
+            Requirement::Capability(Capability::NetRaw),
+        ],
+        source: "bandwhich",
+    },
+    Collector {
+        name: "storage",
+        routes: &["/v1/monitor/storage"],
+        requires: &[Requirement::Command("mount"), Requirement::Command("df")],
+        source: "df",
+    },
+    Collector {
+        name: "storage-io",
+        routes: &[],
+        requires: &[Requirement::Command("iostat")],
+        source: "iostat",
+    },
+    Collector {
+        name: "system",
+        routes: &["/v1/system/information"],
+        requires: &[],
+        source: "sysinfo",
+    },
+    Collector {
+        name: "ipconfig",
+        routes: &["/v1/system/ipconfig"],
+        requires: &[Requirement::Command("networksetup"), Requirement::Command("ifconfig")],
+        source: "networksetup",
+    },
+    Collector {
+        name: "public-ip",
+        routes: &["/v1/ip", "/v2/ip"],
+        requires: &[Requirement::Discovery],
+        source: "stun",
+    },
+    Collector {
+        name: "ram-spec",
+        routes: &["/v1/spec/ram"],
+        requires: &[Requirement::Command("system_profiler")],
+        source: "system_profiler",
+    },
+    Collector {
+        name: "docker",
+        routes: &["/v1/containers"],
+        requires: &[Requirement::DockerSocket],
+        source: "docker-engine",
+    },
+];
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub const COLLECTORS: &[Collector] = &[];
+
+impl Collector {
+    // The requirements that are currently not met.
+    pub fn missing(&self) -> Vec<Missing> {
+        self.requires
+            .iter()
+            .filter(|req| !req.is_met())
+            .map(|req| req.to_missing())
+            .collect()
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &'static str> {
+        self.requires.iter().filter_map(|req| match req {
+            Requirement::Command(cmd) => Some(*cmd),
+            _ => None,
+        })
+    }
+
+    fn serves(&self, path: &str) -> bool {
+        self.routes
+            .iter()
+            .any(|route| path == *route || path.starts_with(&format!("{}/", route)))
+    }
+}
+
+pub fn is_available(name: &str) -> bool {
+    COLLECTORS
+        .iter()
+        .find(|c| c.name == name)
+        .is_none_or(|c| c.missing().is_empty())
+}
+
+// Every command some collector uses, in registry order without duplicates.
+pub fn required_commands() -> Vec<&'static str> {
+    let mut commands: Vec<&'static str> = Vec::new();
+    for cmd in COLLECTORS.iter().flat_map(|c| c.commands()) {
+        if !commands.contains(&cmd) {
+            commands.push(cmd);
+        }
+    }
+    commands
+}
+
+// Names of the collectors that need a command.
+pub fn users_of(command: &str) -> Vec<&'static str> {
+    COLLECTORS
+        .iter()
+        .filter(|c| c.commands().any(|cmd| cmd == command))
+        .map(|c| c.name)
+        .collect()
+}
+
+// The collector behind a path and what it lacks, if that collector is unavailable.
+// Checked per request since commands and the docker socket can appear after startup.
+pub fn unavailable_for(path: &str) -> Option<(&'static Collector, Vec<Missing>)> {
+    COLLECTORS
+        .iter()
+        .filter(|c| c.serves(path))
+        .map(|c| (c, c.missing()))
+        .find(|(_, missing)| !missing.is_empty())
+}
+
+pub fn describe(missing: &[Missing]) -> String {
+    missing
+        .iter()
+        .map(|m| match m.kind {
+            "access" => format!("access to {}", m.name),
+            "command" => format!("command `{}`", m.name),
+            "config" => format!("{} in the configuration", m.name),
+            _ => m.name.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Logs who we run as and which collectors will answer with 503.
+pub fn log_status() {
+    log::log(
+        log::LogLevel::Info,
+        &format!("➜ Running as {}", privilege::describe()),
+    );
+    for collector in COLLECTORS {
+        let missing = collector.missing();
+        if !missing.is_empty() {
+            log::log(
+                log::LogLevel::Warn,
+                &format!("▲ Degraded: {} (missing {})", collector.name, describe(&missing)),
+            );
+        }
+    }
+}
@@ -1,5 +1,9 @@
 // src/core/mod.rs
 
 pub mod bootstrap;
+pub mod cli;
+pub mod collector;
 pub mod response;
-pub mod requirement;
\ No newline at end of file
+pub mod requirement;
+pub mod shutdown;
+pub mod systemd;
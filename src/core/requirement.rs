@@ -1,95 +1,105 @@
 /* src/requirement.rs */
 
 use crate::common::log;
-use std::{env, process};
-use std::process::Command;
-use std::thread;
-use std::time::Duration;
+use crate::core::collector;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::env;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-const MAC_COMMANDS: [&str; 11] = ["df", "iostat", "networksetup", "bandwhich", "ifconfig", "mount", "brew", "netstat", "nettop", "macmon", "fastfetch"];
-const LINUX_COMMANDS: [&str; 9] = ["systemd", "pkg-config", "ip", "dmidecode", "systemd-detect-virt", "iostat", "lsblk", "cpupower", "fastfetch"];
+// Lookups are cached briefly: 503 checks run per request, and a command
+// installed while twig runs is picked up within this interval.
+const LOOKUP_TTL: Duration = Duration::from_secs(30);
 
-pub fn run_dependency_check() {
-    let os = env::consts::OS;
-    let commands_to_check = if os == "macos" {
-        MAC_COMMANDS.as_ref()
-    } else if os == "linux" {
-        LINUX_COMMANDS.as_ref()
-    } else {
-        log::log(log::LogLevel::Error, &format!("✗ Unsupported OS: {}. Cannot check dependencies.", os));
-        process::exit(1);
-    };
+static LOOKUPS: Lazy<DashMap<&'static str, (Option<PathBuf>, Instant)>> = Lazy::new(DashMap::new);
 
-    let mut missing_commands = Vec::new();
-    let mut found_commands = Vec::new();
+// Which of the commands the collectors shell out to are installed.
+pub struct DependencyReport {
+    pub os: &'static str,
+    pub found: Vec<&'static str>,
+    pub missing: Vec<&'static str>,
+}
 
-    for &cmd in commands_to_check {
-        if which(cmd).is_some() {
-            found_commands.push(cmd);
-        } else {
-            missing_commands.push(cmd);
-        }
+pub fn check_dependencies() -> DependencyReport {
+    let (found, missing) = collector::required_commands()
+        .into_iter()
+        .partition::<Vec<&'static str>, _>(|cmd| is_installed(cmd));
+    DependencyReport {
+        os: env::consts::OS,
+        found,
+        missing,
     }
+}
 
-    if !missing_commands.is_empty() {
-        log::log(log::LogLevel::Error, "✗ Dependency check fail");
-        log::log(log::LogLevel::Error, &format!("  ✗ {}", missing_commands.join(", ")));
+pub fn is_installed(cmd: &'static str) -> bool {
+    locate(cmd).is_some()
+}
 
-        if !found_commands.is_empty() {
-            log::log(log::LogLevel::Warn, &format!("  ✓ {}", found_commands.join(", ")));
-        }
+// Full path of a command on PATH.
+pub fn locate(cmd: &'static str) -> Option<PathBuf> {
+    if let Some(entry) = LOOKUPS.get(cmd)
+        && entry.1.elapsed() < LOOKUP_TTL
+    {
+        return entry.0.clone();
+    }
+    let found = which(cmd);
+    LOOKUPS.insert(cmd, (found.clone(), Instant::now()));
+    found
+}
 
-        let install_list = missing_commands.join(" ");
-        if os == "macos" {
-            if which("brew").is_none() {
-                log::log(log::LogLevel::Error, "✗ Homebrew (brew) is not installed.");
-                log::log(log::LogLevel::Error, "➜ Please install it first from github");
-                log::log(log::LogLevel::Warn, "✓ https://github.com/Homebrew/brew");
-            } else {
-                log::log(log::LogLevel::Warn, "➜ Install missing pkg via homebrew");
-                log::log(log::LogLevel::Warn, &format!("  ✓ brew install {}", install_list));
-            }
-        } else if os == "linux" {
-            let distro = get_linux_distro();
-            match distro.as_str() {
-                "ubuntu" | "debian" => {
-                    log::log(log::LogLevel::Warn, "➜ Install missing pkg via apt");
-                    log::log(log::LogLevel::Warn, &format!("  ✓ apt update && apt install {}", install_list));
-                }
-                "arch" | "manjaro" => {
-                    log::log(log::LogLevel::Warn, "➜ Install missing pkg via pacman or yay");
-                    log::log(log::LogLevel::Warn, &format!("  ✓ pacman -Sy {}", install_list));
-                }
-                _ => {
-                    log::log(log::LogLevel::Error, "➜ Please install the missing commands using your system's package manager.");
-                    log::log(log::LogLevel::Warn, "✓ For example, on Fedora you might use `dnf`, on CentOS use `yum`, etc.");
-                }
-            }
-        }
+// The package manager command that installs the missing commands, if one is known.
+pub fn install_command(report: &DependencyReport) -> Option<String> {
+    let install_list = report.missing.join(" ");
+    match report.os {
+        "macos" if which("brew").is_some() => Some(format!("brew install {}", install_list)),
+        "linux" => match get_linux_distro().as_str() {
+            "ubuntu" | "debian" => Some(format!("apt update && apt install {}", install_list)),
+            "arch" | "manjaro" => Some(format!("pacman -Sy {}", install_list)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
-        if let Some(path) = crate::common::log::get_log_path() {
-            thread::sleep(Duration::from_millis(1000));
-            log::log(log::LogLevel::Error, &format!("✗ The crash report can be found at {}", path.display()));
-        }
+// Reports missing commands and the collectors they disable. Nothing here is fatal:
+// the affected endpoints answer 503 until the command is installed.
+pub fn run_dependency_check() {
+    let report = check_dependencies();
+    if report.missing.is_empty() {
+        return;
+    }
 
-        thread::sleep(Duration::from_millis(500));
-        process::exit(1);
+    // The affected collectors are listed by `collector::log_status`.
+    log::log(
+        log::LogLevel::Warn,
+        &format!("▲ Missing dependencies: {}", report.missing.join(", ")),
+    );
+
+    match (report.os, install_command(&report)) {
+        ("macos", None) => {
+            log::log(log::LogLevel::Warn, "➜ Homebrew (brew) is not installed, get it from github");
+            log::log(log::LogLevel::Warn, "  ✓ https://github.com/Homebrew/brew");
+        }
+        (_, Some(command)) => {
+            log::log(log::LogLevel::Warn, "➜ Install missing pkg via your package manager");
+            log::log(log::LogLevel::Warn, &format!("  ✓ {}", command));
+        }
+        (_, None) => {
+            log::log(log::LogLevel::Warn, "➜ Please install the missing commands using your system's package manager.");
+        }
     }
 }
 
-fn which(cmd: &str) -> Option<String> {
-    Command::new("which")
-        .arg(cmd)
-        .output()
-        .ok()
-        .filter(|o| o.status.success())
-        .and_then(|o| {
-            let path = String::from_utf8_lossy(&o.stdout).trim().to_string();
-            if !path.is_empty() {
-                Some(path)
-            } else {
-                None
-            }
+fn which(cmd: &str) -> Option<PathBuf> {
+    env::var_os("PATH")?
+        .to_str()?
+        .split(':')
+        .map(|dir| PathBuf::from(dir).join(cmd))
+        .find(|path| {
+            path.metadata()
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
         })
 }
 
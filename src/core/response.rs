@@ -1,7 +1,7 @@
 // src/core/response.rs
 
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Json, Response},
 };
 use chrono::{SecondsFormat, Utc};
@@ -22,6 +22,25 @@ pub struct PublicErrorResponse {
     timestamp: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublicUnavailableResponse {
+    status: String,
+    message: String,
+    collector: String,
+    missing: serde_json::Value,
+    timestamp: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PublicSkewResponse {
+    status: String,
+    message: String,
+    // Client clock minus server clock, in seconds, as far as the token reveals it.
+    skew: i64,
+    server_time: i64,
+    timestamp: String,
+}
+
 // 200
 pub fn success(data: Option<serde_json::Value>) -> Response {
     let response = PublicSuccessResponse {
@@ -52,9 +71,24 @@ pub fn forbidden() -> Response {
     error(StatusCode::FORBIDDEN, "Access denied")
 }
 
-// 418 I'm a teapot
-pub fn im_a_teapot() -> Response {
-    error(StatusCode::IM_A_TEAPOT, "I'm a teapot")
+// Adds Retry-After to a rejection the client may retry later.
+pub fn retry_after(mut res: Response, wait: std::time::Duration) -> Response {
+    // Rounded up, so a client retrying right on time is not rejected again.
+    let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    res.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+    res
+}
+
+// Marks a response that must not be sent; see `dropped`.
+#[derive(Clone)]
+pub struct Dropped;
+
+// No answer at all: the connection serving the request sees the marker and closes
+// without writing anything, whatever the outer layers added to the response.
+pub fn dropped() -> Response {
+    let mut res = StatusCode::NOT_FOUND.into_response();
+    res.extensions_mut().insert(Dropped);
+    res
 }
 
 // 503 Service Unavailable
@@ -62,6 +96,41 @@ pub fn service_unavailable() -> Response {
     error(StatusCode::SERVICE_UNAVAILABLE, "Service unavailable")
 }
 
+// 503 for an endpoint whose collector lacks a command, capability or access.
+pub fn dependency_unavailable(
+    collector: &str,
+    missing: serde_json::Value,
+    message: impl Into<String>,
+) -> Response {
+    let response = PublicUnavailableResponse {
+        status: "Error".to_string(),
+        message: message.into(),
+        collector: collector.to_string(),
+        missing,
+        timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+    (StatusCode::SERVICE_UNAVAILABLE, Json(response)).into_response()
+}
+
+// 401 for a token from the right key but the wrong time, so the client can fix its clock
+// instead of assuming its key is bad. `/v1/time` gives the server time without a token.
+pub fn clock_skew(skew: i64) -> Response {
+    let direction = if skew < 0 { "behind" } else { "ahead of" };
+    let now = Utc::now();
+    let response = PublicSkewResponse {
+        status: "Error".to_string(),
+        message: format!(
+            "Token outside the accepted time window: client clock is about {}s {} the server",
+            skew.unsigned_abs(),
+            direction
+        ),
+        skew,
+        server_time: now.timestamp(),
+        timestamp: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+    };
+    (StatusCode::UNAUTHORIZED, Json(response)).into_response()
+}
+
 // 401 Unauthorized
 pub fn unauthorized() -> Response {
     error(StatusCode::UNAUTHORIZED, "Unauthorized access")
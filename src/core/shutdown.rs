@@ -0,0 +1,76 @@
+// src/core/shutdown.rs
+
+use crate::common::log;
+use crate::core::systemd;
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+// Flipped once on the first SIGTERM/SIGINT. Blocking collector threads poll the
+// flag, async tasks and servers wait on the channel.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+static NOTIFY: Lazy<watch::Sender<bool>> = Lazy::new(|| watch::channel(false).0);
+
+// Installs the signal handlers. A second signal while draining exits immediately.
+pub fn listen() {
+    let mut terminate = match signal(SignalKind::terminate()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::log(log::LogLevel::Warn, &format!("▲ Cannot install SIGTERM handler: {}", e));
+            return;
+        }
+    };
+    let mut interrupt = match signal(SignalKind::interrupt()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            log::log(log::LogLevel::Warn, &format!("▲ Cannot install SIGINT handler: {}", e));
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        let name = tokio::select! {
+            _ = terminate.recv() => "SIGTERM",
+            _ = interrupt.recv() => "SIGINT",
+        };
+        log::log(log::LogLevel::Info, &format!("➜ Received {}, shutting down...", name));
+        trigger();
+
+        tokio::select! {
+            _ = terminate.recv() => {},
+            _ = interrupt.recv() => {},
+        }
+        log::log(log::LogLevel::Warn, "▲ Second signal received, exiting without draining");
+        log::shutdown();
+        std::process::exit(130);
+    });
+}
+
+// Starts the shutdown sequence: servers stop accepting and collectors wind down.
+pub fn trigger() {
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    NOTIFY.send_replace(true);
+    systemd::stopping();
+    stop_collectors();
+}
+
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+// Resolves once shutdown has been triggered.
+pub async fn signalled() {
+    let mut rx = NOTIFY.subscribe();
+    let _ = rx.wait_for(|stopping| *stopping).await;
+}
+
+// Collectors that poll `is_shutting_down` exit on their next tick; the ones below
+// hold resources that would otherwise outlive the process.
+fn stop_collectors() {
+    crate::modules::monitor::cpu::stop();
+    #[cfg(target_os = "macos")]
+    crate::modules::bandwhich::process::stop();
+}
@@ -0,0 +1,148 @@
+// src/core/systemd.rs
+
+use crate::common::log;
+use crate::core::shutdown;
+use once_cell::sync::Lazy;
+use std::env;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::timeout;
+
+// systemd passes activated sockets starting at this descriptor.
+const LISTEN_FDS_START: RawFd = 3;
+
+// Sockets handed over by systemd, taken once by the server on startup.
+static LISTEN_FDS: Lazy<Mutex<Vec<(OwnedFd, String)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// Sends a state string such as "READY=1" to the service manager. A no-op
+// when not started by systemd with Type=notify.
+pub fn notify(state: &str) {
+    let Ok(path) = env::var("NOTIFY_SOCKET") else { return };
+    let Ok(socket) = UnixDatagram::unbound() else { return };
+
+    let sent = match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            std::os::unix::net::SocketAddr::from_abstract_name(name)
+                .and_then(|addr| socket.send_to_addr(state.as_bytes(), &addr))
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => return,
+        None => socket.send_to(state.as_bytes(), &path),
+    };
+    if let Err(e) = sent {
+        log::log(log::LogLevel::Debug, &format!("▪ sd_notify failed: {}", e));
+    }
+}
+
+pub fn status(message: &str) {
+    notify(&format!("STATUS={}", message));
+}
+
+// Tells systemd startup has finished and starts the watchdog if one is configured.
+pub fn ready(message: &str) {
+    notify(&format!("READY=1\nSTATUS={}", message));
+    spawn_watchdog();
+}
+
+pub fn stopping() {
+    notify("STOPPING=1\nSTATUS=Draining connections");
+}
+
+// Claims the sockets passed via LISTEN_FDS. Must run before other threads are
+// started, since the variables are removed so child processes don't inherit them.
+pub fn take_listen_fds() {
+    let for_us = env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let count = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse::<RawFd>().ok())
+        .unwrap_or(0);
+    let names: Vec<String> = env::var("LISTEN_FDNAMES")
+        .map(|raw| raw.split(':').map(String::from).collect())
+        .unwrap_or_default();
+
+    unsafe {
+        env::remove_var("LISTEN_PID");
+        env::remove_var("LISTEN_FDS");
+        env::remove_var("LISTEN_FDNAMES");
+    }
+    if !for_us || count <= 0 {
+        return;
+    }
+
+    let mut fds = LISTEN_FDS.lock().unwrap();
+    for (index, fd) in (LISTEN_FDS_START..LISTEN_FDS_START + count).enumerate() {
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+        let name = names.get(index).cloned().unwrap_or_default();
+        fds.push((unsafe { OwnedFd::from_raw_fd(fd) }, name));
+    }
+}
+
+// Returns the activated sockets with their FileDescriptorName; empty unless socket-activated.
+pub fn activated_sockets() -> Vec<(OwnedFd, String)> {
+    std::mem::take(&mut *LISTEN_FDS.lock().unwrap())
+}
+
+// Pings at half the configured WatchdogSec, but only while the self-check passes;
+// a stuck runtime stops pinging by itself and systemd restarts the service.
+fn spawn_watchdog() {
+    let interval = env::var("WATCHDOG_USEC")
+        .ok()
+        .and_then(|usec| usec.parse::<u64>().ok())
+        .filter(|usec| *usec > 0)
+        .map(|usec| Duration::from_micros(usec / 2));
+    let for_us = env::var("WATCHDOG_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_none_or(|pid| pid == std::process::id());
+    let Some(interval) = interval.filter(|_| for_us) else {
+        return;
+    };
+
+    log::log(
+        log::LogLevel::Debug,
+        &format!("➜ systemd watchdog every {}ms", interval.as_millis()),
+    );
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut failing = false;
+        while !shutdown::is_shutting_down() {
+            ticker.tick().await;
+            match health_check(interval).await {
+                Ok(()) => {
+                    if failing {
+                        log::log(log::LogLevel::Info, "✓ Health check recovered");
+                        status("Serving");
+                        failing = false;
+                    }
+                    notify("WATCHDOG=1");
+                }
+                Err(e) => {
+                    log::log(log::LogLevel::Warn, &format!("▲ Health check failed: {}", e));
+                    status(&format!("Unhealthy: {}", e));
+                    failing = true;
+                }
+            }
+        }
+    });
+}
+
+// Both the async scheduler and the blocking pool the collectors use must respond
+// well within one watchdog interval.
+async fn health_check(limit: Duration) -> Result<(), String> {
+    let limit = limit / 2;
+    match timeout(limit, tokio::spawn(async {})).await {
+        Ok(Ok(())) => {}
+        _ => return Err("async runtime is not scheduling tasks".to_string()),
+    }
+    match timeout(limit, tokio::task::spawn_blocking(|| ())).await {
+        Ok(Ok(())) => Ok(()),
+        _ => Err("blocking thread pool is saturated".to_string()),
+    }
+}
@@ -3,16 +3,35 @@ mod core;
 mod middlewares;
 mod modules;
 
+use std::time::Duration;
+
 fn main() {
-    common::sudo::check_root();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match core::cli::parse(&args) {
+        Ok(core::cli::Command::Serve) => serve(),
+        Ok(command) => std::process::exit(core::cli::run(command)),
+        Err(e) => {
+            eprintln!("✗ {}\n\n{}", e, core::cli::USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn serve() {
+    // Before any thread exists: this edits the environment.
+    core::systemd::take_listen_fds();
     common::env::load();
     common::log::init();
 
-    tokio::runtime::Builder::new_multi_thread()
+    let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()
-        .unwrap()
-        .block_on(async {
-            core::bootstrap::init().await;
-        });
+        .unwrap();
+    runtime.block_on(async {
+        core::bootstrap::init().await;
+    });
+
+    // Don't wait on blocking collector calls; flush the log buffer last.
+    runtime.shutdown_timeout(Duration::from_secs(1));
+    common::log::shutdown();
 }
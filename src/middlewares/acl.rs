@@ -0,0 +1,90 @@
+// src/middlewares/acl.rs
+
+use crate::common::{env, log};
+use crate::core::response;
+use crate::middlewares::client_ip;
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use dashmap::DashMap;
+use ipnet::IpNet;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::net::IpAddr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+// One `[[acl.rules]]` entry. Rules are checked in order and the first match decides.
+#[derive(Clone, Debug, Serialize)]
+pub struct AclRule {
+    pub action: Action,
+    pub networks: Vec<IpNet>,
+    // Path prefixes the rule is limited to; empty covers the whole API.
+    pub paths: Vec<String>,
+}
+
+impl AclRule {
+    fn matches(&self, ip: IpAddr, path: &str) -> bool {
+        self.networks.iter().any(|net| net.contains(&ip))
+            && (self.paths.is_empty() || self.paths.iter().any(|prefix| under(path, prefix)))
+    }
+
+    // Counters are keyed by what a rule says, so they survive reloads that keep it.
+    fn key(&self) -> String {
+        format!("{:?} {:?} {:?}", self.action, self.networks, self.paths)
+    }
+}
+
+const DEFAULT_KEY: &str = "default";
+
+static HITS: Lazy<DashMap<String, u64>> = Lazy::new(DashMap::new);
+
+// Runs before authentication: a denied address never reaches the token check.
+pub async fn handler(req: Request<Body>, next: Next) -> Response {
+    let Some(ip) = client_ip::of(req.extensions()) else {
+        return next.run(req).await;
+    };
+    let config = env::current();
+    let path = req.uri().path();
+    let (key, action) = match config.acl_rules.iter().find(|rule| rule.matches(ip, path)) {
+        Some(rule) => (rule.key(), rule.action),
+        None => (DEFAULT_KEY.to_string(), config.acl_default),
+    };
+    *HITS.entry(key).or_insert(0) += 1;
+
+    if action == Action::Deny {
+        log::log(log::LogLevel::Debug, &format!("▪ 403: {} denied by network ACL ➜ {}", ip, path));
+        return response::forbidden();
+    }
+    next.run(req).await
+}
+
+// The rules in effect with how often each decided a request.
+pub fn stats() -> serde_json::Value {
+    let config = env::current();
+    let hits = |key: &str| HITS.get(key).map_or(0, |count| *count);
+    let rules: Vec<serde_json::Value> = config
+        .acl_rules
+        .iter()
+        .map(|rule| {
+            let mut value = serde_json::to_value(rule).unwrap_or_default();
+            value["hits"] = hits(&rule.key()).into();
+            value
+        })
+        .collect();
+    serde_json::json!({
+        "default": config.acl_default,
+        "default_hits": hits(DEFAULT_KEY),
+        "rules": rules,
+    })
+}
+
+// Whole segments only, so /v1/docker does not cover /v1/dockerfile.
+fn under(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
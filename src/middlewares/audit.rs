@@ -0,0 +1,63 @@
+// src/middlewares/audit.rs
+
+use crate::common::audit::{self, Detail, Record};
+use crate::middlewares::client_ip;
+use crate::middlewares::identity::Identity;
+use axum::{
+    body::Body,
+    extract::{FromRequestParts, RawPathParams},
+    http::{Method, Request},
+    middleware::Next,
+    response::Response,
+};
+
+// Route layer for mutating endpoints: records who called what and how it went,
+// including calls rejected for a missing scope.
+pub async fn handler(req: Request<Body>, next: Next) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(req).await;
+    }
+
+    let actor = req
+        .extensions()
+        .get::<Identity>()
+        .map_or_else(|| "anonymous".to_string(), |identity| identity.to_string());
+    // Unix socket peers have no address; their identity already names the user.
+    let client_ip = client_ip::of(req.extensions())
+        .map_or_else(|| "local".to_string(), |ip| ip.to_string());
+    let method = req.method().to_string();
+    let endpoint = req.uri().path().to_string();
+
+    // The route's {id} names the target when the handler never ran, e.g. on a scope denial.
+    let (mut parts, body) = req.into_parts();
+    let path_target = RawPathParams::from_request_parts(&mut parts, &())
+        .await
+        .ok()
+        .and_then(|params| params.iter().find(|(key, _)| *key == "id").map(|(_, value)| value.to_string()));
+    let req = Request::from_parts(parts, body);
+
+    let res = next.run(req).await;
+    let detail = res.extensions().get::<Detail>().cloned().or(path_target.map(|target| Detail {
+        target,
+        upstream_status: None,
+    }));
+    let record = Record {
+        actor,
+        client_ip,
+        method,
+        endpoint,
+        status: res.status().as_u16(),
+        detail,
+    };
+    let _ = tokio::task::spawn_blocking(move || audit::append(record)).await;
+    res
+}
+
+// Attaches the target and upstream status for the audit entry to a handler's response.
+pub fn annotate(mut res: Response, target: &str, upstream_status: Option<u16>) -> Response {
+    res.extensions_mut().insert(Detail {
+        target: target.to_string(),
+        upstream_status,
+    });
+    res
+}
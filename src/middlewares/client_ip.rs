@@ -0,0 +1,233 @@
+// src/middlewares/client_ip.rs
+
+use crate::common::env;
+use axum::{
+    body::Body,
+    extract::ConnectInfo,
+    http::{Extensions, HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+// The header trusted proxies put the client address in. Only that one is read:
+// a proxy passes the others through as the client sent them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProxyHeader {
+    // RFC 7239 Forwarded, appended to by each proxy.
+    Forwarded,
+    // X-Forwarded-For, appended to by each proxy (nginx's $proxy_add_x_forwarded_for).
+    XForwardedFor,
+    // X-Real-IP, a single address set by the proxy.
+    XRealIp,
+}
+
+// The address a request is attributed to: the peer itself, or when the peer is a
+// trusted proxy, the client it forwarded the request for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClientIp(pub IpAddr);
+
+// The client address of a request, for middlewares and handlers. Requests that
+// never passed `handler` (e.g. on the Unix socket) fall back to the peer, or None.
+pub fn of(extensions: &Extensions) -> Option<IpAddr> {
+    extensions
+        .get::<ClientIp>()
+        .map(|client| client.0)
+        .or_else(|| extensions.get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip().to_canonical()))
+}
+
+// Resolves the client address once, before anything that limits or logs by it.
+pub async fn handler(mut req: Request<Body>, next: Next) -> Response {
+    if let Some(info) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
+        let ip = resolve(info.0.ip().to_canonical(), req.headers());
+        req.extensions_mut().insert(ClientIp(ip));
+    }
+    next.run(req).await
+}
+
+fn resolve(peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+    let config = env::current();
+    resolve_with(peer, headers, config.proxy_header, &config.trusted_proxies)
+}
+
+fn resolve_with(peer: IpAddr, headers: &HeaderMap, proxy_header: ProxyHeader, proxies: &[IpNet]) -> IpAddr {
+    let trusted = |ip: IpAddr| proxies.iter().any(|net| net.contains(&ip));
+    // Headers from anyone else are whatever the client chose to send.
+    if !trusted(peer) {
+        return peer;
+    }
+
+    let chain = match proxy_header {
+        ProxyHeader::Forwarded => forwarded(headers),
+        ProxyHeader::XForwardedFor => x_forwarded_for(headers),
+        ProxyHeader::XRealIp => {
+            return header(headers, "x-real-ip").and_then(parse_node).unwrap_or(peer);
+        }
+    };
+    let Some(chain) = chain else {
+        return peer;
+    };
+    // Each proxy appends the address it received from, so walk back from the nearest
+    // hop; the first address not itself a trusted proxy is the client.
+    let mut client = peer;
+    for hop in chain.iter().rev() {
+        match hop {
+            Some(ip) => {
+                client = *ip;
+                if !trusted(*ip) {
+                    break;
+                }
+            }
+            // "unknown" or an obfuscated identifier: the last known hop is as far as it goes.
+            None => break,
+        }
+    }
+    client
+}
+
+// RFC 7239: Forwarded: for=192.0.2.60;proto=https, for="[2001:db8::1]:4711"
+fn forwarded(headers: &HeaderMap) -> Option<Vec<Option<IpAddr>>> {
+    let values: Vec<&str> = headers
+        .get_all("forwarded")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    let hops = values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(|element| {
+            element
+                .split(';')
+                .filter_map(|pair| pair.trim().split_once('='))
+                .find(|(key, _)| key.eq_ignore_ascii_case("for"))
+                .and_then(|(_, node)| parse_node(node))
+        })
+        .collect();
+    Some(hops)
+}
+
+// X-Forwarded-For: client, proxy1, proxy2
+fn x_forwarded_for(headers: &HeaderMap) -> Option<Vec<Option<IpAddr>>> {
+    let values: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().flat_map(|value| value.split(',')).map(parse_node).collect())
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+// An address as proxies write it: bare, quoted, with a port, or IPv6 in brackets.
+fn parse_node(raw: &str) -> Option<IpAddr> {
+    let node = raw.trim().trim_matches('"');
+    if let Some(rest) = node.strip_prefix('[') {
+        return rest.split(']').next()?.parse::<IpAddr>().ok().map(|ip| ip.to_canonical());
+    }
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        return Some(ip.to_canonical());
+    }
+    // IPv4 with a port; a bare IPv6 address has more than one colon and parsed above.
+    let (host, port) = node.rsplit_once(':')?;
+    port.parse::<u16>().ok()?;
+    host.parse::<IpAddr>().ok().map(|ip| ip.to_canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER: &str = "10.0.0.1";
+
+    fn proxies() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap()]
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn resolve(peer: &str, pairs: &[(&'static str, &str)], header: ProxyHeader) -> IpAddr {
+        resolve_with(peer.parse().unwrap(), &headers(pairs), header, &proxies())
+    }
+
+    fn ip(raw: &str) -> IpAddr {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn headers_from_untrusted_peers_are_ignored() {
+        let pairs = [("x-forwarded-for", "203.0.113.7")];
+        assert_eq!(resolve("198.51.100.1", &pairs, ProxyHeader::XForwardedFor), ip("198.51.100.1"));
+    }
+
+    #[test]
+    fn x_forwarded_for_walks_back_to_the_first_untrusted_hop() {
+        let pairs = [("x-forwarded-for", "1.1.1.1, 203.0.113.7, 10.0.0.2")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::XForwardedFor), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn x_forwarded_for_spans_repeated_headers() {
+        let pairs = [("x-forwarded-for", "203.0.113.7"), ("x-forwarded-for", "10.0.0.2")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::XForwardedFor), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn only_the_configured_header_is_read() {
+        // The proxy appends X-Forwarded-For and passes the client's Forwarded through.
+        let pairs = [("forwarded", "for=192.0.2.66"), ("x-forwarded-for", "203.0.113.7")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::XForwardedFor), ip("203.0.113.7"));
+        let pairs = [("forwarded", "for=192.0.2.66"), ("x-real-ip", "192.0.2.67")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::XForwardedFor), ip(PEER));
+        let pairs = [("x-forwarded-for", "192.0.2.66"), ("forwarded", "for=203.0.113.7")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::Forwarded), ip("203.0.113.7"));
+    }
+
+    #[test]
+    fn forwarded_elements_and_quoted_ipv6() {
+        let pairs = [("forwarded", r#"for=192.0.2.60;proto=https, For="[2001:db8::1]:4711";by=10.0.0.1"#)];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::Forwarded), ip("2001:db8::1"));
+    }
+
+    #[test]
+    fn an_unknown_hop_stops_at_the_last_known_one() {
+        let pairs = [("forwarded", "for=203.0.113.7, for=unknown")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::Forwarded), ip(PEER));
+        let pairs = [("forwarded", "for=unknown, for=10.0.0.3")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::Forwarded), ip("10.0.0.3"));
+    }
+
+    #[test]
+    fn x_real_ip_is_a_single_address() {
+        let pairs = [("x-real-ip", "203.0.113.7")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::XRealIp), ip("203.0.113.7"));
+        let pairs = [("x-real-ip", "not an address")];
+        assert_eq!(resolve(PEER, &pairs, ProxyHeader::XRealIp), ip(PEER));
+    }
+
+    #[test]
+    fn nodes_as_proxies_write_them() {
+        assert_eq!(parse_node(" 192.0.2.1 "), Some(ip("192.0.2.1")));
+        assert_eq!(parse_node("192.0.2.1:8080"), Some(ip("192.0.2.1")));
+        assert_eq!(parse_node("\"[2001:db8::1]:443\""), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("2001:db8::1"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_node("::ffff:192.0.2.1"), Some(ip("192.0.2.1")));
+        assert_eq!(parse_node("192.0.2.1:http"), None);
+        assert_eq!(parse_node("_hidden"), None);
+        assert_eq!(parse_node("unknown"), None);
+    }
+}
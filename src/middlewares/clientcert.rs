@@ -0,0 +1,43 @@
+// src/middlewares/clientcert.rs
+
+use crate::common::env;
+use crate::middlewares::identity::{Identity, IdentitySource, Scope};
+
+// A client certificate verified against tls.client_ca during the handshake,
+// attached to every request on that connection.
+#[derive(Clone, Debug)]
+pub struct ClientCertificate {
+    pub common_name: Option<String>,
+    // One line, e.g. "CN=hub.rfs.im,O=rfs".
+    pub subject: String,
+    // SHA-256 of the DER encoding, hex.
+    pub fingerprint: String,
+}
+
+// One `[[tls.client_identities]]` entry: which certificate subject gets which scopes.
+#[derive(Clone, Debug)]
+pub struct ClientIdentity {
+    // Matched against the certificate's common name or its full one-line subject.
+    pub subject: String,
+    // Identity name in logs and the audit trail; the subject if not set.
+    pub name: Option<String>,
+    pub scopes: Vec<Scope>,
+}
+
+impl ClientIdentity {
+    fn matches(&self, cert: &ClientCertificate) -> bool {
+        cert.common_name.as_deref() == Some(self.subject.as_str()) || cert.subject == self.subject
+    }
+}
+
+// The identity a verified certificate maps to. A certificate the CA accepts but no
+// entry names grants nothing, so issuing a cert never implicitly grants access.
+pub fn identity(cert: &ClientCertificate) -> Option<Identity> {
+    let config = env::current();
+    let entry = config.tls_client_identities.iter().find(|entry| entry.matches(cert))?;
+    Some(Identity {
+        name: entry.name.clone().unwrap_or_else(|| entry.subject.clone()),
+        source: IdentitySource::ClientCertificate,
+        scopes: entry.scopes.clone(),
+    })
+}
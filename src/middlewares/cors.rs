@@ -7,7 +7,7 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use std::collections::HashSet;
-use crate::common::env::CONFIG;
+use crate::common::env;
 
 pub async fn handler(req: Request, next: Next) -> Response {
     let origin_header = req
@@ -35,19 +35,14 @@ pub async fn handler(req: Request, next: Next) -> Response {
 
 // --- CORS headers to any response ---
 fn add_cors_headers(headers: &mut axum::http::HeaderMap, origin: Option<&str>) {
+    let config = env::current();
     if let Some(origin_str) = origin {
         // public cloud canopy & canmi's private api need
         // whitelist for these trusted domains
-        let mut allowlist = HashSet::from([
-            "rfs.im".to_string(),
-            "*.rfs.im".to_string(),
-            "cloudfaro.com".to_string(),
-            "*.cloudfaro.com".to_string(),
-            "*.canmi.icu".to_string(),
-        ]);
+        let mut allowlist: HashSet<String> = config.cors_allowlist.iter().cloned().collect();
 
         // selfhost
-        let canopy_domain = CONFIG.canopy_domain.trim();
+        let canopy_domain = config.canopy_domain.trim();
         if canopy_domain != "*" {
             allowlist.insert(canopy_domain.to_string());
         }
@@ -60,12 +55,10 @@ fn add_cors_headers(headers: &mut axum::http::HeaderMap, origin: Option<&str>) {
             }
         });
 
-        if matched || canopy_domain == "*" {
-            if let Ok(value) = HeaderValue::from_str(origin_str) {
-                headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
-            }
+        if (matched || canopy_domain == "*") && let Ok(value) = HeaderValue::from_str(origin_str) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
         }
-    } else if CONFIG.canopy_domain.trim() == "*" {
+    } else if config.canopy_domain.trim() == "*" {
         // Allow all if configured, even without an origin header.
         headers.insert(
             header::ACCESS_CONTROL_ALLOW_ORIGIN,
@@ -81,4 +74,9 @@ fn add_cors_headers(headers: &mut axum::http::HeaderMap, origin: Option<&str>) {
         header::ACCESS_CONTROL_ALLOW_HEADERS,
         HeaderValue::from_static("Origin, X-Requested-With, Content-Type, Accept, Authorization"),
     );
+    // Lets browser clients read how long to back off.
+    headers.insert(
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static("RateLimit-Limit, RateLimit-Remaining, RateLimit-Reset, Retry-After"),
+    );
 }
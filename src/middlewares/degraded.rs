@@ -0,0 +1,24 @@
+// src/middlewares/degraded.rs
+
+use crate::common::log;
+use crate::core::{collector, response};
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use serde_json::json;
+
+// Answers 503 for endpoints whose collector lacks a command, capability or access
+// it needs, naming what is missing instead of letting the handler fail obscurely.
+pub async fn handler(req: Request<Body>, next: Next) -> Response {
+    let Some((collector, missing)) = collector::unavailable_for(req.uri().path()) else {
+        return next.run(req).await;
+    };
+    let reason = collector::describe(&missing);
+    log::log(
+        log::LogLevel::Debug,
+        &format!("▪ 503: {} unavailable, missing {}", collector.name, reason),
+    );
+    response::dependency_unavailable(
+        collector.name,
+        json!(missing),
+        format!("{} is unavailable: missing {}", collector.name, reason),
+    )
+}
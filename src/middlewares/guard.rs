@@ -1,11 +1,23 @@
 // src/middlewares/guard.rs
 
+use crate::common::bans::{self, Offence};
 use crate::core::response;
+use crate::middlewares::client_ip;
 use crate::modules::router::whitelist;
 use axum::{body::Body, http::Request, middleware::Next, response::Response};
 use rand::Rng;
 
-const MAX_VERSION: u8 = 2;
+// Highest API version routed; also reported by /v1/capabilities.
+pub const MAX_VERSION: u8 = 2;
+
+// Fetched by browsers and crawlers on their own; answered 404 without counting
+// towards a ban.
+const BENIGN_PATHS: &[&str] = &[
+    "/favicon.ico",
+    "/robots.txt",
+    "/apple-touch-icon.png",
+    "/apple-touch-icon-precomposed.png",
+];
 
 pub async fn handler(req: Request<Body>, next: Next) -> Response {
     let path = req.uri().path();
@@ -16,19 +28,26 @@ pub async fn handler(req: Request<Body>, next: Next) -> Response {
     }
 
     // Check for the /v{N}/... format.
-    if let Some(path_after_v) = path.strip_prefix("/v") {
-        if let Some(slash_index) = path_after_v.find('/') {
-            let version_str = &path_after_v[..slash_index];
-            if let Ok(version) = version_str.parse::<u8>() {
-                // If the version is valid, let the request proceed to the router.
-                if version > 0 && version <= MAX_VERSION {
-                    return next.run(req).await;
-                }
+    if let Some(path_after_v) = path.strip_prefix("/v")
+        && let Some(slash_index) = path_after_v.find('/')
+    {
+        let version_str = &path_after_v[..slash_index];
+        if let Ok(version) = version_str.parse::<u8>() {
+            // If the version is valid, let the request proceed to the router.
+            if version > 0 && version <= MAX_VERSION {
+                return next.run(req).await;
             }
         }
     }
 
+    if BENIGN_PATHS.contains(&path) {
+        return response::not_found();
+    }
+
     // If the path is not a valid versioned API path, block it
+    if let Some(ip) = client_ip::of(req.extensions()) {
+        bans::offence(ip, Offence::Guard, path);
+    }
     let mut rng = rand::thread_rng();
     let roll = rng.gen_range(0..100);
     // 30% Internal Server Error
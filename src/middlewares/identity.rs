@@ -0,0 +1,112 @@
+// src/middlewares/identity.rs
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const PREVIOUS_NODE_KEY: &str = "node-previous";
+
+// Who made a request, attached as a request extension once authentication succeeds.
+#[derive(Clone, Debug)]
+pub struct Identity {
+    pub name: String,
+    pub source: IdentitySource,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IdentitySource {
+    // TOTP-style bearer token derived from the node seeds.
+    NodeKey,
+    // Named key from keys.json, limited to its scopes.
+    ApiKey,
+    // Local process on the Unix socket, identified by SO_PEERCRED.
+    PeerCredential,
+    // TLS client certificate issued by tls.client_ca, mapped in tls.client_identities.
+    ClientCertificate,
+}
+
+// What a credential may do. Routes declare the scope they need in `router::entrance`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "monitor:read")]
+    MonitorRead,
+    #[serde(rename = "containers:read")]
+    ContainersRead,
+    #[serde(rename = "containers:write")]
+    ContainersWrite,
+    #[serde(rename = "system:admin")]
+    SystemAdmin,
+}
+
+impl Scope {
+    pub const ALL: &[Scope] = &[
+        Scope::MonitorRead,
+        Scope::ContainersRead,
+        Scope::ContainersWrite,
+        Scope::SystemAdmin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::MonitorRead => "monitor:read",
+            Scope::ContainersRead => "containers:read",
+            Scope::ContainersWrite => "containers:write",
+            Scope::SystemAdmin => "system:admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Scope> {
+        Scope::ALL.iter().copied().find(|scope| scope.as_str() == value)
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Identity {
+    // The node key is the root credential and holds every scope.
+    pub fn node_key() -> Self {
+        Identity {
+            name: "node".to_string(),
+            source: IdentitySource::NodeKey,
+            scopes: Scope::ALL.to_vec(),
+        }
+    }
+
+    // A client still on the key replaced by the last rotation. It keeps working
+    // but cannot administer the node: a retired key must not rotate to the new one
+    // or mint API keys that outlive its grace period.
+    pub fn previous_node_key() -> Self {
+        Identity {
+            name: PREVIOUS_NODE_KEY.to_string(),
+            source: IdentitySource::NodeKey,
+            scopes: Scope::ALL
+                .iter()
+                .copied()
+                .filter(|scope| *scope != Scope::SystemAdmin)
+                .collect(),
+        }
+    }
+
+    pub fn is_previous_node_key(&self) -> bool {
+        self.source == IdentitySource::NodeKey && self.name == PREVIOUS_NODE_KEY
+    }
+
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.source {
+            IdentitySource::NodeKey => write!(f, "key:{}", self.name),
+            IdentitySource::ApiKey => write!(f, "apikey:{}", self.name),
+            IdentitySource::PeerCredential => write!(f, "peer:{}", self.name),
+            IdentitySource::ClientCertificate => write!(f, "cert:{}", self.name),
+        }
+    }
+}
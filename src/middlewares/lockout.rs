@@ -0,0 +1,129 @@
+// src/middlewares/lockout.rs
+
+use crate::common::audit::{self, Detail, Record};
+use crate::common::{env, log};
+use crate::middlewares::rate_limiting::aggregate;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::time;
+
+// Bans never grow past a day, however often an address comes back.
+const MAX_BAN: Duration = Duration::from_secs(86400);
+// How long an address's ban history is kept for escalating the next ban.
+const MEMORY: Duration = Duration::from_secs(86400);
+
+struct Tracker {
+    // Failures within the current window.
+    failures: u32,
+    window_start: Instant,
+    last_failure: Instant,
+    banned_until: Option<Instant>,
+    bans: u32,
+}
+
+// Keyed like the rate limiter, so an IPv6 client cannot start over from the next
+// address in its prefix.
+static CLIENTS: Lazy<DashMap<IpAddr, Tracker>> = Lazy::new(DashMap::new);
+
+pub fn start_cleanup_task() {
+    tokio::spawn(async {
+        loop {
+            time::sleep(Duration::from_secs(60)).await;
+            let now = Instant::now();
+            CLIENTS.retain(|_, tracker| {
+                tracker.banned_until.is_some_and(|until| until > now)
+                    || tracker.last_failure.elapsed() < MEMORY
+            });
+        }
+    });
+}
+
+// Time left on the address's ban, if it is banned.
+pub fn banned(ip: IpAddr) -> Option<Duration> {
+    let tracker = CLIENTS.get(&aggregate(ip))?;
+    let remaining = tracker.banned_until?.checked_duration_since(Instant::now())?;
+    (!remaining.is_zero()).then_some(remaining)
+}
+
+// Time the address must still wait before its next attempt is even checked.
+// The delay doubles with every failure in the window: none after the first, then 1s, 2s, 4s...
+pub fn backoff_remaining(ip: IpAddr) -> Option<Duration> {
+    let tracker = CLIENTS.get(&aggregate(ip))?;
+    if tracker.failures < 2 {
+        return None;
+    }
+    let delay = Duration::from_secs(1u64 << (tracker.failures - 2).min(16))
+        .min(env::current().lockout_max_backoff);
+    let remaining = (tracker.last_failure + delay).checked_duration_since(Instant::now())?;
+    (!remaining.is_zero()).then_some(remaining)
+}
+
+// Counts a failed authentication and bans the address once it reaches the threshold.
+pub fn failed(ip: IpAddr, path: &str) {
+    let config = env::current();
+    let now = Instant::now();
+    let mut tracker = CLIENTS.entry(aggregate(ip)).or_insert_with(|| Tracker {
+        failures: 0,
+        window_start: now,
+        last_failure: now,
+        banned_until: None,
+        bans: 0,
+    });
+    if now.duration_since(tracker.window_start) > config.lockout_window {
+        tracker.failures = 0;
+        tracker.window_start = now;
+    }
+    tracker.failures += 1;
+    tracker.last_failure = now;
+
+    if tracker.failures < config.lockout_threshold {
+        log::log(
+            log::LogLevel::Debug,
+            &format!("▪ Failed authentication {}/{} from {} on {}", tracker.failures, config.lockout_threshold, ip, path),
+        );
+        return;
+    }
+
+    let ban = config
+        .lockout_ban
+        .saturating_mul(1u32 << tracker.bans.min(16))
+        .min(MAX_BAN);
+    tracker.banned_until = Some(now + ban);
+    tracker.bans += 1;
+    tracker.failures = 0;
+    tracker.window_start = now;
+    let failures = config.lockout_threshold;
+    drop(tracker);
+
+    log::log(
+        log::LogLevel::Warn,
+        &format!(
+            "▲ {} locked out for {}s after {} failed authentications (last on {})",
+            ip,
+            ban.as_secs(),
+            failures,
+            path
+        ),
+    );
+    let record = Record {
+        actor: "system".to_string(),
+        client_ip: ip.to_string(),
+        method: "EVENT".to_string(),
+        endpoint: "auth.lockout".to_string(),
+        status: 403,
+        detail: Some(Detail {
+            target: format!("ban {}s", ban.as_secs()),
+            upstream_status: None,
+        }),
+    };
+    tokio::task::spawn_blocking(move || audit::append(record));
+}
+
+// A successful authentication clears the failure count but keeps the ban history.
+pub fn succeeded(ip: IpAddr) {
+    if let Some(mut tracker) = CLIENTS.get_mut(&aggregate(ip)) {
+        tracker.failures = 0;
+    }
+}
@@ -1,6 +1,6 @@
 // src/middlewares/middleware.rs
 
-use crate::middlewares::{guard, rate_limiting, token, cors};
+use crate::middlewares::{guard, rate_limiting, token, cors, peercred, client_ip, acl};
 use crate::modules::router::blacklist;
 use axum::{middleware, Router};
 
@@ -8,14 +8,29 @@ use axum::{middleware, Router};
 
 // Layers are applied from the outside in. The first `.layer()` call adds the
 // outermost middleware, which will be the first to process a request.
-// Request flow: Rate Limiting -> Whitelist(bypass -> Router) -> Blacklist -> Guard -> Router
+// Request flow: Client IP -> Network ACL -> Rate Limiting -> Whitelist(bypass -> Router) -> Blacklist -> Guard -> Token -> Identity Rate Limiting -> Router
+// Failed tokens feed `lockout`: the token layer enforces its back-off, the blacklist its bans.
+// Scope and degraded-collector checks are route layers, see `router::entrance`.
 pub fn stack(router: Router) -> Router {
     router
+        .layer(middleware::from_fn(rate_limiting::identity_handler))
         .layer(middleware::from_fn(token::handler))
         .layer(middleware::from_fn(guard::handler))
         .layer(middleware::from_fn(blacklist::handler))
         // whitelist changed to pure list, skip logic move to blacklist and guard
         //.layer(middleware::from_fn(whitelist::handler))
         .layer(middleware::from_fn(rate_limiting::handler))
+        .layer(middleware::from_fn(acl::handler))
+        .layer(middleware::from_fn(client_ip::handler))
         .layer(middleware::from_fn(cors::handler))
 }
+
+
+// Stack for the local Unix socket listener. Connections never cross the network,
+// so rate limiting, CORS and the bearer token are replaced by peer credentials.
+// Request flow: Peer credentials -> Guard -> Router
+pub fn local_stack(router: Router) -> Router {
+    router
+        .layer(middleware::from_fn(guard::handler))
+        .layer(middleware::from_fn(peercred::handler))
+}
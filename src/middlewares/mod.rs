@@ -5,4 +5,14 @@ pub mod middleware;
 pub mod rate_limiting;
 pub mod router;
 pub mod token;
-pub mod cors;
\ No newline at end of file
+pub mod cors;
+pub mod client_ip;
+pub mod acl;
+pub mod identity;
+pub mod clientcert;
+pub mod audit;
+pub mod peercred;
+pub mod scope;
+pub mod signature;
+pub mod lockout;
+pub mod degraded;
@@ -0,0 +1,102 @@
+// src/middlewares/peercred.rs
+
+use crate::common::env;
+use crate::common::log;
+use crate::core::response;
+use crate::middlewares::identity::{Identity, IdentitySource, Scope};
+use axum::{
+    body::Body,
+    extract::{connect_info::Connected, ConnectInfo},
+    http::Request,
+    middleware::Next,
+    response::Response,
+    serve::IncomingStream,
+};
+use std::ffi::CStr;
+use tokio::net::UnixListener;
+
+// Credentials of the process on the other end of a Unix socket connection.
+#[derive(Clone, Copy, Debug)]
+pub struct UnixPeer {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub pid: Option<i32>,
+}
+
+impl Connected<IncomingStream<'_, UnixListener>> for UnixPeer {
+    fn connect_info(stream: IncomingStream<'_, UnixListener>) -> Self {
+        match stream.io().peer_cred() {
+            Ok(cred) => UnixPeer {
+                uid: Some(cred.uid()),
+                gid: Some(cred.gid()),
+                pid: cred.pid(),
+            },
+            Err(_) => UnixPeer {
+                uid: None,
+                gid: None,
+                pid: None,
+            },
+        }
+    }
+}
+
+// Replaces the bearer token check for Unix socket connections: the kernel-reported
+// peer uid becomes the identity. Root and the user twig runs as are always accepted;
+// anyone else only when UNIX_SOCKET_USERS lists them by name or uid.
+pub async fn handler(
+    ConnectInfo(peer): ConnectInfo<UnixPeer>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(uid) = peer.uid else {
+        log::log(log::LogLevel::Debug, "▪ 403: unix peer credentials unavailable");
+        return response::forbidden();
+    };
+    let user = username(uid).unwrap_or_else(|| uid.to_string());
+
+    let config = env::current();
+    let allowed = uid == 0
+        || uid == unsafe { libc::geteuid() }
+        || config
+            .unix_socket_users
+            .iter()
+            .any(|entry| *entry == user || *entry == uid.to_string());
+    if !allowed {
+        log::log(
+            log::LogLevel::Debug,
+            &format!("▪ 403: unix peer {} (uid {}, gid {:?}) not allowed", user, uid, peer.gid),
+        );
+        return response::forbidden();
+    }
+
+    let identity = Identity {
+        name: user,
+        source: IdentitySource::PeerCredential,
+        // Access to the socket is already restricted by its mode and UNIX_SOCKET_USERS.
+        scopes: Scope::ALL.to_vec(),
+    };
+    log::log(
+        log::LogLevel::Debug,
+        &format!(
+            "➜ {} (pid {}) {} {}",
+            identity,
+            peer.pid.map_or("?".to_string(), |p| p.to_string()),
+            req.method(),
+            req.uri().path()
+        ),
+    );
+    req.extensions_mut().insert(identity);
+    next.run(req).await
+}
+
+fn username(uid: u32) -> Option<String> {
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let rc = unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(pwd.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
@@ -1,12 +1,14 @@
 // src/middlewares/rate_limiting.rs
 
-use crate::common::log;
+use crate::common::bans::{self, Offence};
+use crate::common::{env, log};
 use crate::core::response;
+use crate::middlewares::client_ip;
+use crate::middlewares::identity::Identity;
 use crate::middlewares::router::{self, RateLimitRule};
 use axum::{
     body::Body,
-    extract::ConnectInfo,
-    http::{Request, StatusCode},
+    http::{HeaderMap, HeaderName, HeaderValue, Request, StatusCode},
     middleware::Next,
     response::Response,
 };
@@ -14,95 +16,279 @@ use dashmap::DashMap;
 use lazy_static::lazy_static;
 use std::{
     collections::HashMap,
-    net::SocketAddr,
+    fmt,
+    net::{IpAddr, Ipv6Addr},
     sync::Arc,
     time::{Duration, Instant},
 };
 use tokio::time;
 
+// Who a bucket belongs to. Addresses rather than sockets, so opening new
+// connections from fresh source ports doesn't reset the limit.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum ClientKey {
+    // IPv6 addresses are aggregated to rate_limit.ipv6_prefix, as one host usually holds a whole /64.
+    Ip(IpAddr),
+    Identity(String),
+}
+
+impl fmt::Display for ClientKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientKey::Ip(IpAddr::V6(ip)) => {
+                let prefix = env::current().rate_limit_ipv6_prefix;
+                if prefix < 128 { write!(f, "{}/{}", ip, prefix) } else { write!(f, "{}", ip) }
+            }
+            ClientKey::Ip(ip) => write!(f, "{}", ip),
+            ClientKey::Identity(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+// What a client has left under a rule, sent as the IETF draft RateLimit-* headers.
+struct Quota {
+    limit: u32,
+    remaining: u32,
+    // Until the client could send a full burst again.
+    reset: Duration,
+}
+
+const LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
 struct WarningTracker {
     first_seen: Instant,
     hits: HashMap<String, u32>,
 }
 
 lazy_static! {
-    static ref RULES: (HashMap<&'static str, RateLimitRule>, RateLimitRule) = router::get_rules();
-    static ref PATH_RULES: &'static HashMap<&'static str, RateLimitRule> = &RULES.0;
-    static ref DEFAULT_RULE: &'static RateLimitRule = &RULES.1;
-    static ref CLIENTS: Arc<DashMap<SocketAddr, Vec<Instant>>> = Arc::new(DashMap::new());
-    static ref WARN_POOL: Arc<DashMap<SocketAddr, WarningTracker>> = Arc::new(DashMap::new());
-    static ref LAST_LOGGED_REQUEST: Arc<DashMap<SocketAddr, (String, String)>> = Arc::new(DashMap::new());
+    // GCRA state per client and rule: the theoretical arrival time of the next request.
+    static ref BUCKETS: Arc<DashMap<(ClientKey, String), Instant>> = Arc::new(DashMap::new());
+    static ref WARN_POOL: Arc<DashMap<ClientKey, WarningTracker>> = Arc::new(DashMap::new());
+    static ref LAST_LOGGED_REQUEST: Arc<DashMap<IpAddr, (String, String, Instant)>> = Arc::new(DashMap::new());
 }
 
 pub fn start_cleanup_task() {
-    let clients = Arc::clone(&CLIENTS);
+    let buckets = Arc::clone(&BUCKETS);
     let warn_pool = Arc::clone(&WARN_POOL);
     let last_logged = Arc::clone(&LAST_LOGGED_REQUEST);
     tokio::spawn(async move {
         loop {
             time::sleep(Duration::from_secs(10)).await;
-            clients.retain(|_, timestamps| {
-                timestamps.last().map_or(false, |last| last.elapsed() < Duration::from_secs(300))
-            });
+            // A bucket whose arrival time has passed is full, the same as no bucket.
+            let now = Instant::now();
+            buckets.retain(|_, tat| *tat > now);
             warn_pool.retain(|_, tracker| {
                 tracker.first_seen.elapsed() < Duration::from_secs(600)
             });
-            last_logged.retain(|_, _| true);
+            last_logged.retain(|_, (_, _, at)| at.elapsed() < Duration::from_secs(300));
         }
     });
 }
 
-pub async fn handler(
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
-    req: Request<Body>,
-    next: Next,
-) -> Response {
+// Limits every request by client address, before authentication.
+pub async fn handler(req: Request<Body>, next: Next) -> Response {
+    let Some(ip) = client_ip::of(req.extensions()) else {
+        return next.run(req).await;
+    };
     let path = req.uri().path().to_string();
     let method = req.method().as_str().to_string();
 
     // ➜ conditional log if different from last request
     let mut should_log = true;
-    if let Some((last_method, last_path)) = LAST_LOGGED_REQUEST.get(&addr).map(|r| r.clone()) {
-        if last_method == method && last_path == path {
-            should_log = false;
-        }
+    if let Some(last) = LAST_LOGGED_REQUEST.get(&ip)
+        && last.0 == method
+        && last.1 == path
+    {
+        should_log = false;
     }
     if should_log {
         log::log(log::LogLevel::Debug, &format!("➜ {} {}", method, path));
-        LAST_LOGGED_REQUEST.insert(addr, (method.clone(), path.clone()));
+        LAST_LOGGED_REQUEST.insert(ip, (method.clone(), path.clone(), Instant::now()));
     }
 
-    let rule = PATH_RULES.get(path.as_str()).unwrap_or(&DEFAULT_RULE);
+    let client = ClientKey::Ip(aggregate(ip));
+    let (rule_name, rule) = router::rule_for(&path);
+    let quota = match acquire(&client, &rule_name, &rule) {
+        Ok(quota) => quota,
+        Err((quota, wait)) => return limited(client, &path, &quota, wait),
+    };
+    let mut res = next.run(req).await;
+    set_headers(res.headers_mut(), &quota);
+    res
+}
+
+// Limits authenticated clients by identity, across every address they connect from.
+// Runs after the token check, on top of the per-address limits.
+pub async fn identity_handler(req: Request<Body>, next: Next) -> Response {
+    let Some(identity) = req.extensions().get::<Identity>().map(|i| i.to_string()) else {
+        return next.run(req).await;
+    };
+    let Some(rule) = router::identity_rule(&identity) else {
+        return next.run(req).await;
+    };
+    let client = ClientKey::Identity(identity);
+    let quota = match acquire(&client, "identity", &rule) {
+        Ok(quota) => quota,
+        Err((quota, wait)) => return limited(client, req.uri().path(), &quota, wait),
+    };
+    let mut res = next.run(req).await;
+    set_headers(res.headers_mut(), &quota);
+    res
+}
+
+// GCRA: each request pushes the client's arrival time forward by period/limit,
+// and a request is admitted while that time is at most one period ahead, which
+// allows bursts of up to `limit`. On refusal, also returns how long until one would pass.
+fn acquire(client: &ClientKey, rule_name: &str, rule: &RateLimitRule) -> Result<Quota, (Quota, Duration)> {
     let now = Instant::now();
-    let mut client_timestamps = CLIENTS.entry(addr).or_insert_with(Vec::new);
-    client_timestamps.retain(|&t| now.duration_since(t) < rule.period);
+    let mut tat = BUCKETS
+        .entry((client.clone(), rule_name.to_string()))
+        .or_insert(now);
+    let (next, verdict) = gcra(*tat, now, rule);
+    *tat = next;
+    verdict
+}
 
-    if client_timestamps.len() >= rule.limit as usize {
-        log::log(log::LogLevel::Debug, &format!("▪ {} hit limit ➜ {}", addr, path));
+// One GCRA step: the arrival time to store, and whether the request at `now` passes.
+fn gcra(tat: Instant, now: Instant, rule: &RateLimitRule) -> (Instant, Result<Quota, (Quota, Duration)>) {
+    let interval = rule.period / rule.limit.max(1);
+    let tolerance = rule.period.saturating_sub(interval);
+    let start = tat.max(now);
+    let ahead = start.duration_since(now);
+    if ahead > tolerance {
+        let quota = Quota { limit: rule.limit, remaining: 0, reset: ahead };
+        return (tat, Err((quota, ahead - tolerance)));
+    }
+    let ahead = ahead + interval;
+    // Requests that still fit before the arrival time is a full period ahead.
+    let remaining = (rule.period.saturating_sub(ahead).as_nanos() / interval.as_nanos().max(1)) as u32;
+    (start + interval, Ok(Quota { limit: rule.limit, remaining, reset: ahead }))
+}
 
-        {
-            let mut tracker = WARN_POOL.entry(addr).or_insert_with(|| WarningTracker {
-                first_seen: Instant::now(),
-                hits: HashMap::new(),
-            });
+// Sets the RateLimit-* headers, unless a tighter limit already did: a request
+// passes both the address and the identity limiter, and the lower quota binds.
+fn set_headers(headers: &mut HeaderMap, quota: &Quota) {
+    let tighter = headers
+        .get(&REMAINING)
+        .and_then(|value| value.to_str().ok()?.parse::<u32>().ok())
+        .is_some_and(|remaining| remaining < quota.remaining);
+    if tighter {
+        return;
+    }
+    headers.insert(LIMIT, HeaderValue::from(quota.limit));
+    headers.insert(REMAINING, HeaderValue::from(quota.remaining));
+    headers.insert(RESET, HeaderValue::from(ceil_secs(quota.reset)));
+}
 
-            *tracker.hits.entry(path.clone()).or_insert(0) += 1;
-            let total_hits: u32 = tracker.hits.values().sum();
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+fn limited(client: ClientKey, path: &str, quota: &Quota, wait: Duration) -> Response {
+    log::log(log::LogLevel::Debug, &format!("▪ {} hit limit ➜ {}", client, path));
+
+    let mut tracker = WARN_POOL.entry(client.clone()).or_insert_with(|| WarningTracker {
+        first_seen: Instant::now(),
+        hits: HashMap::new(),
+    });
+
+    *tracker.hits.entry(path.to_string()).or_insert(0) += 1;
+    let total_hits: u32 = tracker.hits.values().sum();
+
+    if total_hits >= 3 {
+        log::log(log::LogLevel::Warn, &format!("▲ {} triggered rate limit warning", client));
+        for (p, c) in tracker.hits.iter() {
+            log::log(log::LogLevel::Warn, &format!("  ➜ {} +{}", p, c));
+        }
+        drop(tracker);
+        WARN_POOL.remove(&client);
+        if let ClientKey::Ip(ip) = client {
+            bans::offence(ip, Offence::RateLimit, path);
+        }
+    }
+
+    let mut res = response::retry_after(
+        response::error(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded."),
+        wait,
+    );
+    set_headers(res.headers_mut(), quota);
+    res
+}
 
-            if total_hits >= 3 {
-                log::log(log::LogLevel::Warn, &format!("▲ {} triggered rate limit warning", addr));
-                for (p, c) in tracker.hits.iter() {
-                    log::log(log::LogLevel::Warn, &format!("  ➜ {} +{}", p, c));
-                }
-                drop(tracker);
-                WARN_POOL.remove(&addr);
+// The address a client is limited and locked out under: IPv4 as is, IPv6 masked to the configured prefix.
+pub fn aggregate(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return IpAddr::V4(v4);
             }
+            let prefix = env::current().rate_limit_ipv6_prefix;
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask))
         }
+        v4 => v4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        return response::error(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded.");
+    fn rule(limit: u32, secs: u64) -> RateLimitRule {
+        RateLimitRule { period: Duration::from_secs(secs), limit }
+    }
+
+    #[test]
+    fn admits_a_full_burst_then_refuses() {
+        let rule = rule(5, 10);
+        let now = Instant::now();
+        let mut tat = now;
+        for expected in (0..5).rev() {
+            let (next, verdict) = gcra(tat, now, &rule);
+            let quota = verdict.ok().expect("request within the burst");
+            assert_eq!(quota.remaining, expected);
+            tat = next;
+        }
+        let (next, verdict) = gcra(tat, now, &rule);
+        let (quota, wait) = verdict.err().expect("request past the burst");
+        assert_eq!(quota.remaining, 0);
+        assert_eq!(quota.reset, Duration::from_secs(10));
+        assert_eq!(wait, Duration::from_secs(2));
+        // A refused request does not move the arrival time.
+        assert_eq!(next, tat);
     }
 
-    client_timestamps.push(now);
-    drop(client_timestamps);
-    next.run(req).await
+    #[test]
+    fn admits_again_after_one_interval() {
+        let rule = rule(5, 10);
+        let now = Instant::now();
+        let full = now + Duration::from_secs(10);
+        assert!(gcra(full, now + Duration::from_secs(1), &rule).1.is_err());
+        let (next, verdict) = gcra(full, now + Duration::from_secs(2), &rule);
+        assert_eq!(verdict.ok().map(|quota| quota.remaining), Some(0));
+        assert_eq!(next, full + Duration::from_secs(2));
+    }
+
+    #[test]
+    fn an_idle_client_starts_from_now() {
+        let rule = rule(4, 8);
+        let now = Instant::now();
+        let (next, verdict) = gcra(now - Duration::from_secs(60), now, &rule);
+        let quota = verdict.ok().expect("idle client is admitted");
+        assert_eq!(quota.remaining, 3);
+        assert_eq!(quota.reset, Duration::from_secs(2));
+        assert_eq!(next, now + Duration::from_secs(2));
+    }
+
+    #[test]
+    fn a_limit_of_one_allows_no_burst() {
+        let rule = rule(1, 5);
+        let now = Instant::now();
+        let (next, verdict) = gcra(now, now, &rule);
+        assert_eq!(verdict.ok().map(|quota| quota.remaining), Some(0));
+        let (_, verdict) = gcra(next, now, &rule);
+        assert_eq!(verdict.err().map(|(_, wait)| wait), Some(Duration::from_secs(5)));
+    }
 }
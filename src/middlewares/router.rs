@@ -1,40 +1,62 @@
 // src/middlewares/router.rs
 
-use std::collections::HashMap;
+use crate::common::env;
 use std::time::Duration;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct RateLimitRule {
     pub period: Duration,
     pub limit: u32,
 }
 
-// rate limit rules
-pub fn get_rules() -> (HashMap<&'static str, RateLimitRule>, RateLimitRule) {
-    let mut path_rules = HashMap::new();
+// Finds the configured rule for a path, falling back to the default rule. The
+// name returned with it keys the rule's bucket, so each rule is counted separately.
+pub fn rule_for(path: &str) -> (String, RateLimitRule) {
+    let config = env::current();
+    config
+        .rate_limit_rules
+        .iter()
+        .find(|(rule_path, _)| rule_path == path)
+        .cloned()
+        .unwrap_or_else(|| ("*".to_string(), config.rate_limit_default.clone()))
+}
 
-    // --- Define path-specific rules here ---
-    path_rules.insert(
-        "/",
-        RateLimitRule {
-            period: Duration::from_secs(1),
-            limit: 5,
-        },
-    );
+// The limit for an authenticated identity, as written in logs (e.g. `apikey:hub`),
+// falling back to rate_limit.identity_default. None leaves it to the address limits.
+pub fn identity_rule(identity: &str) -> Option<RateLimitRule> {
+    let config = env::current();
+    config
+        .rate_limit_identities
+        .iter()
+        .find(|(name, _)| name == identity)
+        .map(|(_, rule)| rule.clone())
+        .or_else(|| config.rate_limit_identity_default.clone())
+}
 
-    path_rules.insert(
-        "/v1/system/information",
-        RateLimitRule {
-            period: Duration::from_secs(3),
-            limit: 15,
-        },
-    );
+// Path-specific rules used when the config file defines none.
+pub fn default_path_rules() -> Vec<(String, RateLimitRule)> {
+    vec![
+        (
+            "/".to_string(),
+            RateLimitRule {
+                period: Duration::from_secs(1),
+                limit: 5,
+            },
+        ),
+        (
+            "/v1/system/information".to_string(),
+            RateLimitRule {
+                period: Duration::from_secs(3),
+                limit: 15,
+            },
+        ),
+    ]
+}
 
-    // --- Define the default rule for all other paths ---
-    let default_rule = RateLimitRule {
+// The rule for all other paths.
+pub fn default_rule() -> RateLimitRule {
+    RateLimitRule {
         period: Duration::from_secs(1),
         limit: 10,
-    };
-
-    (path_rules, default_rule)
+    }
 }
@@ -0,0 +1,48 @@
+// src/middlewares/scope.rs
+
+use crate::common::{env, log};
+use crate::core::response;
+use crate::middlewares::identity::{Identity, Scope};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+
+// Route layer: the authenticated identity must hold the route's scope.
+pub async fn handler(State(scope): State<Scope>, req: Request<Body>, next: Next) -> Response {
+    match req.extensions().get::<Identity>() {
+        Some(identity) if identity.allows(scope) => next.run(req).await,
+        Some(identity) => {
+            log::log(
+                log::LogLevel::Debug,
+                &format!("▪ 403: {} lacks {} for {}", identity, scope, req.uri().path()),
+            );
+            response::error(StatusCode::FORBIDDEN, format!("Missing scope {}", scope))
+        }
+        // Only development skips authentication and leaves no identity behind.
+        None if is_development() => next.run(req).await,
+        None => response::forbidden(),
+    }
+}
+
+// Route layer for admin routes: the previous node key is refused whatever its scopes.
+pub async fn current_key_only(req: Request<Body>, next: Next) -> Response {
+    match req.extensions().get::<Identity>() {
+        Some(identity) if identity.is_previous_node_key() => {
+            log::log(
+                log::LogLevel::Debug,
+                &format!("▪ 403: {} may not use {}", identity, req.uri().path()),
+            );
+            response::error(StatusCode::FORBIDDEN, "The previous node key cannot administer the node")
+        }
+        _ => next.run(req).await,
+    }
+}
+
+fn is_development() -> bool {
+    let stage = env::current().stage.to_lowercase();
+    stage == "development" || stage == "dev"
+}
@@ -0,0 +1,150 @@
+// src/middlewares/signature.rs
+
+use crate::common::{env, log, setup};
+use crate::common::setup::KeyGeneration;
+use crate::core::response;
+use crate::middlewares::identity::Identity;
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    response::Response,
+};
+use chrono::Utc;
+use dashmap::DashMap;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use tokio::time;
+
+// Authorization: TWIG-HMAC-SHA256 timestamp=<unix secs>, nonce=<16-128 chars>, signature=<hex>
+//
+// The signature is HMAC-SHA256 over
+//   METHOD \n PATH \n QUERY \n hex(sha256(body)) \n TIMESTAMP \n NONCE
+// keyed with sha256("twig-request-signing" || node seeds), where QUERY is the raw
+// query string without '?' (empty if none). A signed request is only valid once.
+pub const SCHEME: &str = "TWIG-HMAC-SHA256 ";
+
+// Signed bodies are buffered to be hashed; container operations carry none.
+const MAX_BODY: usize = 1024 * 1024;
+
+// Nonces seen within the accepted clock skew, with when they may be forgotten.
+static NONCES: Lazy<DashMap<String, Instant>> = Lazy::new(DashMap::new);
+
+struct Params<'a> {
+    timestamp: i64,
+    nonce: &'a str,
+    signature: Vec<u8>,
+}
+
+pub fn start_cleanup_task() {
+    tokio::spawn(async {
+        loop {
+            time::sleep(Duration::from_secs(30)).await;
+            let now = Instant::now();
+            NONCES.retain(|_, expires| *expires > now);
+        }
+    });
+}
+
+// Verifies a signed request and returns it with the node identity attached.
+pub async fn verify(req: Request<Body>, header: &str) -> Result<Request<Body>, Response> {
+    let Some(params) = parse(header) else {
+        log::log(log::LogLevel::Debug, "▪ 403: malformed signature header");
+        return Err(response::forbidden());
+    };
+
+    let skew = env::current().signature_skew;
+    let age = Utc::now().timestamp().abs_diff(params.timestamp);
+    if age > skew.as_secs() {
+        log::log(
+            log::LogLevel::Debug,
+            &format!("▪ 403: signature timestamp off by {}s", age),
+        );
+        return Err(response::forbidden());
+    }
+
+    let (parts, body) = req.into_parts();
+    let body = match to_bytes(body, MAX_BODY).await {
+        Ok(body) => body,
+        Err(_) => return Err(response::error(StatusCode::PAYLOAD_TOO_LARGE, "Signed body too large")),
+    };
+    let canonical = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        parts.method,
+        parts.uri.path(),
+        parts.uri.query().unwrap_or(""),
+        hex(&Sha256::digest(&body)),
+        params.timestamp,
+        params.nonce
+    );
+
+    let generation = setup::signing_keys().into_iter().find_map(|(generation, key)| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key).ok()?;
+        mac.update(canonical.as_bytes());
+        mac.verify_slice(&params.signature).ok().map(|_| generation)
+    });
+    let Some(generation) = generation else {
+        log::log(
+            log::LogLevel::Debug,
+            &format!("▪ 403: signature mismatch for {} {}", parts.method, parts.uri.path()),
+        );
+        return Err(response::forbidden());
+    };
+
+    // Only checked once the signature is valid, so forged requests cannot burn nonces.
+    // A nonce must outlive the window in which its timestamp is still accepted.
+    let expires = Instant::now() + skew * 2;
+    if NONCES.insert(params.nonce.to_string(), expires).is_some() {
+        log::log(
+            log::LogLevel::Warn,
+            &format!("▲ Replayed signed request rejected: {} {}", parts.method, parts.uri.path()),
+        );
+        return Err(response::forbidden());
+    }
+
+    let mut req = Request::from_parts(parts, Body::from(body));
+    req.extensions_mut().insert(match generation {
+        KeyGeneration::Current => Identity::node_key(),
+        KeyGeneration::Previous => Identity::previous_node_key(),
+    });
+    Ok(req)
+}
+
+fn parse(header: &str) -> Option<Params<'_>> {
+    let mut timestamp = None;
+    let mut nonce = None;
+    let mut signature = None;
+    for pair in header.split(',') {
+        let (key, value) = pair.trim().split_once('=')?;
+        match key {
+            "timestamp" => timestamp = value.parse::<i64>().ok(),
+            "nonce" => nonce = Some(value),
+            "signature" => signature = from_hex(value),
+            _ => return None,
+        }
+    }
+    let nonce = nonce.filter(|n| {
+        (16..=128).contains(&n.len())
+            && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })?;
+    Some(Params {
+        timestamp: timestamp?,
+        nonce,
+        signature: signature?,
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -2,32 +2,90 @@
 
 use axum::{
     body::Body,
-    http::Request,
+    http::{Request, StatusCode},
     middleware::Next,
     response::Response,
 };
-use crate::common::setup::compute_token_windows;
+use crate::common::setup::{match_token, token_skew, KeyGeneration};
 use crate::core::response;
 use crate::common::{log};
-use crate::common::env::CONFIG;
+use crate::common::{apikeys, env};
+use crate::middlewares::clientcert::{self, ClientCertificate};
+use crate::middlewares::identity::Identity;
+use crate::middlewares::{client_ip, lockout, signature};
+use crate::modules::router::whitelist;
 
 pub async fn handler(req: Request<Body>, next: Next) -> Response {
-    if req.uri().path() == "/" {
+    if whitelist::PUBLIC_PATHS.contains(&req.uri().path()) {
         return next.run(req).await;
     }
 
-    let stage = CONFIG.stage.to_lowercase();
+    let stage = env::current().stage.to_lowercase();
     if stage == "development" || stage == "dev" {
         log::log(log::LogLevel::Debug, "➜ skip auth");
         return next.run(req).await;
     }
 
+    let ip = client_ip::of(req.extensions());
+    if let Some(ip) = ip
+        && let Some(wait) = lockout::backoff_remaining(ip)
+    {
+        log::log(
+            log::LogLevel::Debug,
+            &format!("▪ 429: {} must wait {}s after failed authentication", ip, wait.as_secs()),
+        );
+        return response::retry_after(
+            response::error(StatusCode::TOO_MANY_REQUESTS, "Too many failed authentication attempts"),
+            wait,
+        );
+    }
+
+    let path = req.uri().path().to_string();
+    match authenticate(req).await {
+        Ok(req) => {
+            if let Some(ip) = ip {
+                lockout::succeeded(ip);
+            }
+            next.run(req).await
+        }
+        // Includes tokens from a skewed clock, which would otherwise let a client
+        // probe far outside the window at no cost.
+        Err(res) => {
+            if let Some(ip) = ip {
+                lockout::failed(ip, &path);
+            }
+            res
+        }
+    }
+}
+
+// Checks the credential and attaches the identity, or returns the rejection.
+async fn authenticate(mut req: Request<Body>) -> Result<Request<Body>, Response> {
+    // Verified during the TLS handshake; only a mapped certificate authenticates.
+    if let Some(cert) = req.extensions().get::<ClientCertificate>() {
+        match clientcert::identity(cert) {
+            Some(identity) => {
+                req.extensions_mut().insert(identity);
+                return Ok(req);
+            }
+            None => log::log(
+                log::LogLevel::Debug,
+                &format!("▪ client certificate {} ({}) maps to no identity", cert.subject, cert.fingerprint),
+            ),
+        }
+    }
+
     let raw_header = req.headers().get("authorization");
     let header_str = raw_header.and_then(|v| v.to_str().ok());
 
+    if let Some(params) = header_str.and_then(|h| h.strip_prefix(signature::SCHEME)) {
+        let params = params.to_string();
+        return signature::verify(req, &params).await;
+    }
+
     if raw_header.is_none() {
         log::log(log::LogLevel::Debug, "▪ 403: no authorization header");
-        return response::forbidden();
+        return Err(response::forbidden());
     }
 
     if header_str.is_none() || !header_str.unwrap().starts_with("Bearer ") {
@@ -35,22 +93,52 @@ pub async fn handler(req: Request<Body>, next: Next) -> Response {
             log::LogLevel::Debug,
             &format!("▪ 403: invalid header format: {:?}", header_str),
         );
-        return response::forbidden();
+        return Err(response::forbidden());
     }
 
     let token = header_str.unwrap().strip_prefix("Bearer ").unwrap();
-    let tokens = compute_token_windows();
+    if token.starts_with(apikeys::PREFIX) {
+        return match apikeys::authenticate(token) {
+            Some(key) => {
+                req.extensions_mut().insert(key.identity());
+                Ok(req)
+            }
+            None => {
+                log::log(log::LogLevel::Debug, "▪ 403: unknown or revoked API key");
+                Err(response::forbidden())
+            }
+        };
+    }
 
-    if tokens.iter().any(|valid| token == valid) {
-        next.run(req).await
-    } else {
-        log::log(
-            log::LogLevel::Debug,
-            &format!(
-                "▪ 403: token mismatch, received: {}",
-                token
-            ),
-        );
-        response::forbidden()
+    match match_token(token) {
+        Some(KeyGeneration::Current) => {
+            req.extensions_mut().insert(Identity::node_key());
+            Ok(req)
+        }
+        Some(KeyGeneration::Previous) => {
+            log::log(
+                log::LogLevel::Debug,
+                &format!("▪ {} authenticated with the previous node key", req.uri().path()),
+            );
+            req.extensions_mut().insert(Identity::previous_node_key());
+            Ok(req)
+        }
+        None => {
+            if let Some(skew) = token_skew(token) {
+                log::log(
+                    log::LogLevel::Debug,
+                    &format!("▪ 401: token from a clock {}s off", skew),
+                );
+                return Err(response::clock_skew(skew));
+            }
+            log::log(
+                log::LogLevel::Debug,
+                &format!(
+                    "▪ 403: token mismatch, received: {}",
+                    token
+                ),
+            );
+            Err(response::forbidden())
+        }
     }
 }
@@ -0,0 +1,54 @@
+// src/modules/app/capabilities.rs
+
+use crate::core::{collector, response};
+use crate::middlewares::guard;
+use crate::modules::cpu::power;
+use axum::response::Response;
+use serde_json::json;
+
+// Container operations exposed under /v1/containers/{id}/...
+const CONTAINER_OPERATIONS: &[&str] = &["start", "stop", "pause", "resume", "restart", "kill", "delete"];
+
+// What this node can report and control, so clients can hide what it can't.
+pub async fn get_capabilities_handler() -> Response {
+    let mut collectors = Vec::new();
+    for c in collector::COLLECTORS {
+        let missing = c.missing();
+        let mut available = missing.is_empty();
+        let mut source = Some(c.source.to_string());
+
+        // The power interface depends on the CPU vendor and kernel drivers, so
+        // probe it instead of reporting the generic source.
+        if c.name == "cpu-power" && available {
+            match power::fetch_cpu_power().await {
+                Ok(info) => source = Some(info.source),
+                Err(_) => {
+                    available = false;
+                    source = None;
+                }
+            }
+        }
+
+        collectors.push(json!({
+            "name": c.name,
+            "available": available,
+            "source": source,
+            "routes": c.routes,
+            "missing": missing,
+        }));
+    }
+
+    let controls = json!([{
+        "name": "containers",
+        "available": collector::is_available("docker"),
+        "operations": CONTAINER_OPERATIONS,
+    }]);
+
+    let versions: Vec<String> = (1..=guard::MAX_VERSION).map(|v| format!("v{}", v)).collect();
+
+    response::success(Some(json!({
+        "api_versions": versions,
+        "collectors": collectors,
+        "controls": controls,
+    })))
+}
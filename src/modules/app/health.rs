@@ -0,0 +1,63 @@
+// src/modules/app/health.rs
+
+use crate::core::{collector, response, shutdown};
+use axum::{http::StatusCode, response::Response};
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+static STARTED: Lazy<Instant> = Lazy::new(Instant::now);
+// Set once every listener is bound; cleared again when shutdown starts.
+static READY: AtomicBool = AtomicBool::new(false);
+
+// Records the start time for the uptime figure.
+pub fn init() {
+    Lazy::force(&STARTED);
+}
+
+pub fn mark_ready() {
+    READY.store(true, Ordering::SeqCst);
+}
+
+pub fn is_ready() -> bool {
+    READY.load(Ordering::SeqCst) && !shutdown::is_shutting_down()
+}
+
+// Liveness and readiness for load balancers and orchestrators. Served without a
+// token; answering at all means the process is alive, 503 means not ready.
+// Degraded collectors are reported but do not fail readiness.
+pub async fn get_health_handler() -> Response {
+    let collectors: serde_json::Map<String, serde_json::Value> = collector::COLLECTORS
+        .iter()
+        .map(|c| {
+            let state = if c.missing().is_empty() { "up" } else { "degraded" };
+            (c.name.to_string(), json!(state))
+        })
+        .collect();
+    let degraded = collectors.values().any(|state| state == "degraded");
+
+    let ready = is_ready();
+    let status = if shutdown::is_shutting_down() {
+        "stopping"
+    } else if !ready {
+        "starting"
+    } else if degraded {
+        "degraded"
+    } else {
+        "ok"
+    };
+
+    let mut res = response::success(Some(json!({
+        "status": status,
+        "live": true,
+        "ready": ready,
+        "version": env!("CARGO_PKG_VERSION"),
+        "uptime": STARTED.elapsed().as_secs(),
+        "collectors": collectors,
+    })));
+    if !ready {
+        *res.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    }
+    res
+}
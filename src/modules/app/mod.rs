@@ -1,3 +1,6 @@
 // src/modules/app/mod.rs
 
+pub mod capabilities;
+pub mod health;
 pub mod root;
+pub mod time;
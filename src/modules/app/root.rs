@@ -8,7 +8,8 @@ use crate::common::{env};
 // Handles requests to the root endpoint and returns project information.
 pub async fn get_root_handler() -> Response {
     let cargo_version = env!("CARGO_PKG_VERSION");
-    let stage_raw = &env::CONFIG.stage;
+    let config = env::current();
+    let stage_raw = &config.stage;
     let stage = match stage_raw.to_lowercase().as_str() {
         "dev" | "development" => "Preview".to_string(),
         _ => "Production".to_string(),
@@ -0,0 +1,23 @@
+// src/modules/app/time.rs
+
+use crate::common::env;
+use crate::core::response;
+use axum::response::Response;
+use chrono::{SecondsFormat, Utc};
+use serde_json::json;
+
+// Server time and token timing, served without a token so clients whose tokens are
+// rejected for clock skew can measure their offset and compensate.
+pub async fn get_time_handler() -> Response {
+    let config = env::current();
+    let now = Utc::now();
+    response::success(Some(json!({
+        "time": now.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "unix": now.timestamp(),
+        "unix_ms": now.timestamp_millis(),
+        "token": {
+            "step": config.token_step,
+            "window": config.token_window,
+        },
+    })))
+}
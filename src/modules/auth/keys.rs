@@ -0,0 +1,73 @@
+// src/modules/auth/keys.rs
+
+use crate::common::apikeys::{self, KeyError};
+use crate::core::response;
+use crate::middlewares::audit;
+use crate::middlewares::identity::Scope;
+use axum::{
+    body::Bytes,
+    extract::Path,
+    http::{header, HeaderValue, StatusCode},
+    response::Response,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize)]
+struct CreateRequest {
+    name: String,
+    scopes: Vec<String>,
+}
+
+pub async fn get_keys_handler() -> Response {
+    response::success(Some(json!({ "keys": apikeys::list() })))
+}
+
+// Creates a named key. The token is only in this response.
+pub async fn post_key_handler(body: Bytes) -> Response {
+    let request = match serde_json::from_slice::<CreateRequest>(&body) {
+        Ok(request) => request,
+        Err(e) => return response::error(StatusCode::BAD_REQUEST, format!("Invalid body: {}", e)),
+    };
+    let mut scopes = Vec::new();
+    for raw in &request.scopes {
+        match Scope::parse(raw) {
+            Some(scope) if !scopes.contains(&scope) => scopes.push(scope),
+            Some(_) => {}
+            None => {
+                let known: Vec<&str> = Scope::ALL.iter().map(Scope::as_str).collect();
+                return response::error(
+                    StatusCode::BAD_REQUEST,
+                    format!("Unknown scope '{}', expected one of {}", raw, known.join(", ")),
+                );
+            }
+        }
+    }
+
+    match apikeys::create(&request.name, scopes) {
+        Ok((key, token)) => {
+            let id = key.id.clone();
+            let mut res = response::success(Some(json!({ "key": key, "token": token })));
+            res.headers_mut()
+                .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+            audit::annotate(res, &id, None)
+        }
+        Err(e) => error(e),
+    }
+}
+
+pub async fn delete_key_handler(Path(id): Path<String>) -> Response {
+    let res = match apikeys::revoke(&id) {
+        Ok(key) => response::success(Some(json!({ "revoked": key }))),
+        Err(e) => error(e),
+    };
+    audit::annotate(res, &id, None)
+}
+
+fn error(e: KeyError) -> Response {
+    match e {
+        KeyError::Invalid(e) => response::error(StatusCode::BAD_REQUEST, e),
+        KeyError::NotFound(e) => response::error(StatusCode::NOT_FOUND, e),
+        KeyError::Io(e) => response::error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
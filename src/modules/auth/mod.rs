@@ -0,0 +1,4 @@
+// src/modules/auth/mod.rs
+
+pub mod keys;
+pub mod rotate;
@@ -0,0 +1,48 @@
+// src/modules/auth/rotate.rs
+
+use crate::common::{env, setup};
+use crate::core::response;
+use crate::middlewares::audit;
+use axum::{body::Bytes, http::header, http::HeaderValue, http::StatusCode, response::Response};
+use chrono::SecondsFormat;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+#[derive(Deserialize, Default)]
+struct RotateRequest {
+    // Seconds the old key stays valid; `auth.rotation_grace` when omitted.
+    grace: Option<u64>,
+}
+
+// Rotates the node key and returns the new one. This is the only time it is
+// shown, so the response must not be cached anywhere.
+pub async fn post_rotate_handler(body: Bytes) -> Response {
+    let request = if body.is_empty() {
+        RotateRequest::default()
+    } else {
+        match serde_json::from_slice::<RotateRequest>(&body) {
+            Ok(request) => request,
+            Err(e) => return response::error(StatusCode::BAD_REQUEST, format!("Invalid body: {}", e)),
+        }
+    };
+    let grace = request
+        .grace
+        .map(Duration::from_secs)
+        .unwrap_or(env::current().rotation_grace);
+
+    let result = tokio::task::spawn_blocking(move || setup::rotate_token(grace)).await;
+    let rotation = match result {
+        Ok(Ok(rotation)) => rotation,
+        Ok(Err(e)) => return response::error(StatusCode::INTERNAL_SERVER_ERROR, e),
+        Err(_) => return response::internal_error(),
+    };
+
+    let mut res = response::success(Some(json!({
+        "key": rotation.key(),
+        "previous_valid_until": rotation.previous_valid_until.map(|until| until.to_rfc3339_opts(SecondsFormat::Secs, true)),
+    })));
+    res.headers_mut()
+        .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    audit::annotate(res, "node-key", None)
+}
@@ -0,0 +1,93 @@
+// src/modules/axum/connection.rs
+
+use crate::common::log;
+use crate::core::response::Dropped;
+use crate::core::shutdown;
+use axum::{extract::ConnectInfo, Extension, Router};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper::Request;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::{GracefulShutdown, Watcher};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+use tower::Service;
+
+// How long to stop accepting after an error that is not about a single connection.
+pub const ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+// Accepts plain HTTP connections and serves the router on each.
+// On shutdown, accepting stops and open connections are asked to close gracefully.
+pub async fn serve_plain(listener: TcpListener, app: Router) {
+    let graceful = GracefulShutdown::new();
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown::signalled() => break,
+        };
+        let (stream, peer) = match accepted {
+            Ok(conn) => conn,
+            Err(e) if is_connection_error(&e) => {
+                log::log(log::LogLevel::Debug, &format!("▪ Accept error: {}", e));
+                continue;
+            }
+            // Out of file descriptors and the like: retrying at once would spin.
+            Err(e) => {
+                log::log(log::LogLevel::Warn, &format!("▲ Accept error, pausing {}s: {}", ACCEPT_BACKOFF.as_secs(), e));
+                tokio::select! {
+                    _ = tokio::time::sleep(ACCEPT_BACKOFF) => continue,
+                    _ = shutdown::signalled() => break,
+                }
+            }
+        };
+        let app = app.clone().layer(Extension(ConnectInfo::<SocketAddr>(peer)));
+        tokio::spawn(serve(stream, app, graceful.watcher()));
+    }
+    graceful.shutdown().await;
+}
+
+// Serves one connection over HTTP/1 or HTTP/2. A response marked `Dropped` is never
+// written: the connection is closed instead, with every other request on it.
+pub async fn serve<I>(io: I, app: Router, watcher: Watcher)
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let close = Arc::new(Notify::new());
+    let signal = close.clone();
+    let service = service_fn(move |req: Request<Incoming>| {
+        // The router is always ready, so it can be called without polling first.
+        let mut app = app.clone();
+        let signal = signal.clone();
+        async move {
+            let res = app.call(req).await?;
+            if res.extensions().get::<Dropped>().is_some() {
+                signal.notify_one();
+                // The connection goes away before this would resolve.
+                std::future::pending::<()>().await;
+            }
+            Ok::<_, Infallible>(res)
+        }
+    });
+    let builder = Builder::new(TokioExecutor::new());
+    let conn = builder.serve_connection_with_upgrades(TokioIo::new(io), service);
+    tokio::select! {
+        _ = watcher.watch(conn) => {}
+        _ = close.notified() => {}
+    }
+}
+
+// Errors that concern one connection only, as `axum::serve` classifies them.
+pub fn is_connection_error(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::ConnectionRefused
+            | std::io::ErrorKind::ConnectionAborted
+            | std::io::ErrorKind::ConnectionReset
+    )
+}
@@ -1,67 +1,185 @@
 // src/modules/axum/core.rs
 
+use crate::common::env;
 use crate::common::log;
+use crate::core::{shutdown, systemd};
+use crate::modules::app;
+use crate::modules::ip::discovery;
+use crate::modules::axum::connection;
+use crate::modules::axum::listener::{self, BoundListener};
+use crate::modules::axum::local::{self, LocalListener};
 use crate::modules::router::entrance::app_router;
+use socket2::Socket;
 use std::net::{IpAddr, SocketAddr};
-use tokio::net::TcpListener;
-use tokio::time::{timeout, Duration};
+use std::os::fd::OwnedFd;
+use tokio::time::timeout;
 
 pub async fn start() {
     let app = app_router();
-    let port = 30721;
-    let addr = format!("0.0.0.0:{}", port);
+    let config = env::current();
 
-    // Bind the listener to the address.
-    let listener = match TcpListener::bind(&addr).await {
-        Ok(listener) => listener,
-        Err(e) => {
+    // Sockets passed in by systemd replace the configured addresses;
+    // otherwise bind every configured address, reporting failures one by one.
+    let activated = systemd::activated_sockets();
+    let (mut listeners, locals) = if activated.is_empty() {
+        (
+            listener::bind_all(&config.listen),
+            local::bind_all(&listener::unix_socket_paths(&config.listen)),
+        )
+    } else {
+        log::log(
+            log::LogLevel::Info,
+            &format!("➜ Using {} socket(s) passed by systemd", activated.len()),
+        );
+        adopt_activated(activated)
+    };
+
+    // HTTPS listeners are dropped if no certificate can be loaded.
+    #[cfg(feature = "tls")]
+    if listeners.iter().any(|l| l.tls)
+        && let Err(e) = super::tls::init()
+    {
+        log::log(log::LogLevel::Error, &format!("✗ TLS unavailable: {}", e));
+        listeners.retain(|l| {
+            if l.tls {
+                log::log(log::LogLevel::Error, &format!("✗ Not serving https://{}", l.addr));
+            }
+            !l.tls
+        });
+    }
+
+    if listeners.is_empty() && locals.is_empty() {
+        log::log(log::LogLevel::Error, "✗ No listener could be bound, server not started");
+        return;
+    }
+
+    // --- Log Addresses ---
+
+    if let Some(first) = listeners.first() {
+        spawn_public_ip_probe(first.scheme(), first.addr.port());
+    }
+
+    log_listening(&listeners, &locals);
+
+    log::log(log::LogLevel::Info, "✓ Ready to handle requests");
+    app::health::mark_ready();
+    systemd::ready(&format!(
+        "Serving on {} listener(s)",
+        listeners.len() + locals.len()
+    ));
+
+    let mut servers: Vec<_> = locals
+        .into_iter()
+        .map(|local| tokio::spawn(local::serve(local)))
+        .collect();
+    servers.extend(listeners.into_iter().map(|bound| {
+        let app = app.clone();
+        tokio::spawn(async move {
+            #[cfg(feature = "tls")]
+            if bound.tls {
+                super::tls::serve(bound.listener, app).await;
+                return;
+            }
+
+            connection::serve_plain(bound.listener, app).await;
+        })
+    }));
+
+    // Servers return once shutdown is signalled and their connections have drained;
+    // connections still open at the deadline are dropped.
+    let drained = futures::future::join_all(servers);
+    tokio::pin!(drained);
+    tokio::select! {
+        _ = &mut drained => return,
+        _ = shutdown::signalled() => {}
+    }
+    log::log(log::LogLevel::Info, "➜ Stopped accepting, draining in-flight requests...");
+    let shutdown_timeout = env::current().shutdown_timeout;
+    match timeout(shutdown_timeout, &mut drained).await {
+        Ok(_) => log::log(log::LogLevel::Info, "✓ All connections drained"),
+        Err(_) => log::log(
+            log::LogLevel::Warn,
+            &format!(
+                "▲ Drain deadline of {}s reached, closing remaining connections",
+                shutdown_timeout.as_secs()
+            ),
+        ),
+    }
+}
+
+// Sorts systemd sockets into TCP and Unix listeners. A TCP socket is served over
+// TLS when its FileDescriptorName starts with "https".
+fn adopt_activated(sockets: Vec<(OwnedFd, String)>) -> (Vec<BoundListener>, Vec<LocalListener>) {
+    let mut listeners = Vec::new();
+    let mut locals = Vec::new();
+    for (fd, name) in sockets {
+        let socket = Socket::from(fd);
+        let adopted = socket.local_addr().and_then(|addr| {
+            let tls = name.starts_with("https");
+            if let Some(addr) = addr.as_socket() {
+                if tls && !cfg!(feature = "tls") {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        "https requires building with the tls feature",
+                    ));
+                }
+                listener::adopt(socket, addr, tls).map(|bound| listeners.push(bound))
+            } else if let Some(path) = addr.as_pathname().map(|p| p.to_path_buf()) {
+                local::adopt(socket, path).map(|local| locals.push(local))
+            } else {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "unsupported socket address family",
+                ))
+            }
+        });
+        if let Err(e) = adopted {
             log::log(
                 log::LogLevel::Error,
-                &format!("✗ Failed to bind to address {}: {}", addr, e),
+                &format!("✗ Cannot use systemd socket '{}': {}", name, e),
             );
-            return;
         }
-    };
-
-    // --- Log Addresses ---
+    }
+    (listeners, locals)
+}
 
-    // Spawn a non-blocking task to find the public IP with a 5s timeout.
-    // This prevents the startup sequence from being blocked by a slow network call.
+// Spawns a non-blocking task to find the public IP with a 5s timeout.
+// This prevents the startup sequence from being blocked by a slow network call.
+fn spawn_public_ip_probe(scheme: &'static str, port: u16) {
+    if !discovery::is_enabled() {
+        log::log(log::LogLevel::Debug, "➜ Public address discovery disabled");
+        return;
+    }
     tokio::spawn(async move {
-        let public_ip_future = tokio::process::Command::new("curl")
-            .arg("-s") // silent mode
-            .arg("ifconfig.me")
-            .output();
-
-        match timeout(Duration::from_secs(5), public_ip_future).await {
-            Ok(Ok(output)) if output.status.success() => {
-                let ip_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                if !ip_str.is_empty() && ip_str.parse::<IpAddr>().is_ok() {
-                    log::log(
-                        log::LogLevel::Info,
-                        &format!("• Possible Public Network: http://{}:{}", ip_str, port),
-                    );
+        match discovery::public_addresses().await {
+            Ok(found) => {
+                let urls = found
+                    .ipv4
+                    .map(|ip| format!("{}://{}:{}", scheme, ip, port))
+                    .into_iter()
+                    .chain(found.ipv6.map(|ip| format!("{}://[{}]:{}", scheme, ip, port)));
+                for url in urls {
+                    log::log(log::LogLevel::Info, &format!("• Possible Public Network: {}", url));
                 }
             }
-            Err(_) => {
-                log::log(
-                    log::LogLevel::Warn,
-                    "➜ Timed out fetching public IP address (5s limit).",
-                );
-            }
-            _ => {
-                // Command failed or produced non-UTF8 output. Do nothing.
+            Err(e) => {
+                log::log(log::LogLevel::Warn, &format!("➜ Public address unknown: {}", e));
             }
         }
     });
+}
 
-    log::log(
-        log::LogLevel::Info,
-        &format!("✓ Listening on http://localhost:{}", port),
-    );
+// Logs the URLs the server is actually reachable on.
+fn log_listening(listeners: &[BoundListener], locals: &[LocalListener]) {
+    for local in locals {
+        log::log(
+            log::LogLevel::Info,
+            &format!("✓ Listening on unix:{}", local.path.display()),
+        );
+    }
 
-    // Get all non-loopback IP addresses.
-    let all_ips: Vec<IpAddr> = get_if_addrs::get_if_addrs()
+    // Wildcard binds are expanded to the interface addresses of their family.
+    let interface_ips: Vec<IpAddr> = get_if_addrs::get_if_addrs()
         .map(|interfaces| {
             interfaces
                 .into_iter()
@@ -71,67 +189,73 @@ pub async fn start() {
         })
         .unwrap_or_default();
 
-    if !all_ips.is_empty() {
-        // Sort the collected IPs with custom priority.
-        let mut sorted_ips = all_ips;
-        sorted_ips.sort_by_key(|ip| match ip {
-            IpAddr::V4(ipv4) => {
-                let octets = ipv4.octets();
-                if octets[0] == 192 && octets[1] == 168 {
-                    (0, ip.to_string()) // L0: 192.168.x.x
-                } else if octets[0] == 100 {
-                    (1, ip.to_string()) // L1: 100.x.x.x
-                } else if octets[0] == 10 {
-                    (2, ip.to_string()) // L2: 10.x.x.x
-                } else {
-                    (3, ip.to_string()) // L3: Other IPv4
-                }
-            }
-            IpAddr::V6(_) => (4, ip.to_string()), // L4: IPv6
-        });
+    let mut endpoints: Vec<(&str, SocketAddr)> = Vec::new();
+    for bound in listeners {
+        let ip = bound.addr.ip();
+        if !ip.is_unspecified() {
+            log::log(
+                log::LogLevel::Info,
+                &format!("✓ Listening on {}", format_url(bound.scheme(), &bound.addr)),
+            );
+            continue;
+        }
 
-        let display_limit = 2;
-        let ips_to_display = &sorted_ips[..display_limit.min(sorted_ips.len())];
-        let more_count = sorted_ips.len() - ips_to_display.len();
+        log::log(
+            log::LogLevel::Info,
+            &format!("✓ Listening on {}://localhost:{}", bound.scheme(), bound.addr.port()),
+        );
+        for iface_ip in &interface_ips {
+            let matches_family = iface_ip.is_ipv4() == ip.is_ipv4() || bound.dual_stack;
+            let endpoint = (bound.scheme(), SocketAddr::new(*iface_ip, bound.addr.port()));
+            if matches_family && !endpoints.contains(&endpoint) {
+                endpoints.push(endpoint);
+            }
+        }
+    }
 
-        for (index, ip_addr) in ips_to_display.iter().enumerate() {
-            let url = match ip_addr {
-                IpAddr::V4(ip) => format!("http://{}:{}", ip, port),
-                IpAddr::V6(ip) => format!("http://[{}]:{}", ip, port),
-            };
+    if endpoints.is_empty() {
+        return;
+    }
 
-            let mut display_str = format!("✓ Listening on {}", url);
-            if index == ips_to_display.len() - 1 && more_count > 0 {
-                display_str.push_str(&format!(" +{} more", more_count));
+    // Sort the collected addresses with custom priority.
+    endpoints.sort_by_key(|(_, addr)| match addr.ip() {
+        IpAddr::V4(ipv4) => {
+            let octets = ipv4.octets();
+            if octets[0] == 192 && octets[1] == 168 {
+                (0, addr.to_string()) // L0: 192.168.x.x
+            } else if octets[0] == 100 {
+                (1, addr.to_string()) // L1: 100.x.x.x
+            } else if octets[0] == 10 {
+                (2, addr.to_string()) // L2: 10.x.x.x
+            } else {
+                (3, addr.to_string()) // L3: Other IPv4
             }
-
-            log::log(log::LogLevel::Info, &display_str);
         }
+        IpAddr::V6(_) => (4, addr.to_string()), // L4: IPv6
+    });
 
-        // If there are more addresses, log them at the debug level.
-        if more_count > 0 {
-            let hidden_ips = &sorted_ips[display_limit..];
-            for ip_addr in hidden_ips {
-                let url = match ip_addr {
-                    IpAddr::V4(ip) => format!("http://{}:{}", ip, port),
-                    IpAddr::V6(ip) => format!("http://[{}]:{}", ip, port),
-                };
-                log::log(log::LogLevel::Debug, &format!("➜ Listening on {}", url));
-            }
+    let display_limit = 2;
+    let to_display = &endpoints[..display_limit.min(endpoints.len())];
+    let more_count = endpoints.len() - to_display.len();
+
+    for (index, (scheme, addr)) in to_display.iter().enumerate() {
+        let mut display_str = format!("✓ Listening on {}", format_url(scheme, addr));
+        if index == to_display.len() - 1 && more_count > 0 {
+            display_str.push_str(&format!(" +{} more", more_count));
         }
+        log::log(log::LogLevel::Info, &display_str);
     }
 
-    log::log(log::LogLevel::Info, "✓ Ready to handle requests");
-
-    if let Err(e) = axum::serve(
-        listener,
-        app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await
-    {
+    // If there are more addresses, log them at the debug level.
+    for (scheme, addr) in &endpoints[to_display.len()..] {
         log::log(
-            log::LogLevel::Error,
-            &format!("✗ Axum server error: {}", e),
+            log::LogLevel::Debug,
+            &format!("➜ Listening on {}", format_url(scheme, addr)),
         );
     }
 }
+
+fn format_url(scheme: &str, addr: &SocketAddr) -> String {
+    // SocketAddr already brackets IPv6 hosts, e.g. [::1]:30721
+    format!("{}://{}", scheme, addr)
+}
@@ -0,0 +1,141 @@
+// src/modules/axum/listener.rs
+
+use crate::common::log;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use tokio::net::TcpListener;
+
+pub const DEFAULT_PORT: u16 = 30721;
+
+#[derive(Clone, Copy)]
+pub struct ListenSpec {
+    pub addr: SocketAddr,
+    pub tls: bool,
+}
+
+pub struct BoundListener {
+    pub addr: SocketAddr,
+    pub tls: bool,
+    // True when an unspecified IPv6 socket also accepts IPv4-mapped clients.
+    pub dual_stack: bool,
+    pub listener: TcpListener,
+}
+
+impl BoundListener {
+    pub fn scheme(&self) -> &'static str {
+        if self.tls { "https" } else { "http" }
+    }
+}
+
+// Parses a listen entry: an optional http:// or https:// scheme followed by a bind address.
+pub fn parse_spec(raw: &str) -> Result<ListenSpec, String> {
+    let raw = raw.trim();
+    if let Some(rest) = raw.strip_prefix("https://") {
+        if !cfg!(feature = "tls") {
+            return Err(format!("'{}' requires TLS support, which is not compiled in", raw));
+        }
+        return Ok(ListenSpec { addr: parse_addr(rest)?, tls: true });
+    }
+    let rest = raw.strip_prefix("http://").unwrap_or(raw);
+    Ok(ListenSpec { addr: parse_addr(rest)?, tls: false })
+}
+
+// Unix socket entries are written as unix:/run/twig.sock and served by the local listener.
+pub fn unix_socket_paths(raw_addrs: &[String]) -> Vec<PathBuf> {
+    raw_addrs
+        .iter()
+        .filter_map(|raw| raw.trim().strip_prefix("unix:"))
+        .map(PathBuf::from)
+        .collect()
+}
+
+// Parses a bind address. Accepted forms:
+//   0.0.0.0:30721, [::]:30721, 192.168.1.2, ::1, *:30721 (dual-stack), :30721
+pub fn parse_addr(raw: &str) -> Result<SocketAddr, String> {
+    let raw = raw.trim();
+    if let Ok(addr) = raw.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    if let Ok(ip) = raw.trim_matches(|c| c == '[' || c == ']').parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, DEFAULT_PORT));
+    }
+    if let Some(port) = raw.strip_prefix("*:") {
+        let port = port.parse::<u16>().map_err(|_| format!("invalid port in '{}'", raw))?;
+        return Ok(SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port));
+    }
+    if let Some(port) = raw.strip_prefix(':') {
+        let port = port.parse::<u16>().map_err(|_| format!("invalid port in '{}'", raw))?;
+        return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port));
+    }
+    Err(format!("'{}' is not a valid bind address", raw))
+}
+
+// Binds every configured address, logging a clear error for each one that fails.
+// An unspecified IPv6 address ([::]) is bound dual-stack unless 0.0.0.0 is also
+// configured on the same port, in which case it is restricted to IPv6 only.
+pub fn bind_all(raw_addrs: &[String]) -> Vec<BoundListener> {
+    let mut specs: Vec<ListenSpec> = Vec::new();
+    for raw in raw_addrs.iter().filter(|raw| !raw.trim().starts_with("unix:")) {
+        match parse_spec(raw) {
+            Ok(spec) if specs.iter().any(|s| s.addr == spec.addr) => {
+                log::log(log::LogLevel::Warn, &format!("▲ Duplicate listen address {}, skipped", spec.addr));
+            }
+            Ok(spec) => specs.push(spec),
+            Err(e) => {
+                log::log(log::LogLevel::Error, &format!("✗ Invalid listen address: {}", e));
+            }
+        }
+    }
+
+    let mut bound = Vec::new();
+    for spec in &specs {
+        let addr = &spec.addr;
+        let dual_stack = match addr.ip() {
+            IpAddr::V6(ip) if ip.is_unspecified() => !specs.iter().any(|other| {
+                other.addr.port() == addr.port() && other.addr.ip() == IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+            }),
+            _ => false,
+        };
+
+        match bind(*addr, dual_stack) {
+            Ok(listener) => bound.push(BoundListener {
+                addr: *addr,
+                tls: spec.tls,
+                dual_stack,
+                listener,
+            }),
+            Err(e) => {
+                log::log(
+                    log::LogLevel::Error,
+                    &format!("✗ Failed to bind to address {}: {}", addr, e),
+                );
+            }
+        }
+    }
+    bound
+}
+
+// Wraps an already listening TCP socket, e.g. one passed in by systemd.
+pub fn adopt(socket: Socket, addr: SocketAddr, tls: bool) -> std::io::Result<BoundListener> {
+    let dual_stack = addr.ip().is_unspecified() && addr.is_ipv6() && !socket.only_v6()?;
+    socket.set_nonblocking(true)?;
+    Ok(BoundListener {
+        addr,
+        tls,
+        dual_stack,
+        listener: TcpListener::from_std(socket.into())?,
+    })
+}
+
+fn bind(addr: SocketAddr, dual_stack: bool) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(!dual_stack)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
@@ -0,0 +1,132 @@
+// src/modules/axum/local.rs
+
+use crate::common::env;
+use crate::common::log;
+use crate::core::shutdown;
+use crate::middlewares::peercred::UnixPeer;
+use crate::modules::router::entrance::local_router;
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::os::fd::OwnedFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use socket2::Socket;
+use tokio::net::UnixListener;
+
+pub struct LocalListener {
+    pub path: PathBuf,
+    pub listener: UnixListener,
+    // Whether the socket file was created by us and should be removed on exit.
+    pub owned: bool,
+}
+
+// Binds every unix: entry, applying the configured mode and group to the socket file.
+pub fn bind_all(paths: &[PathBuf]) -> Vec<LocalListener> {
+    let mut bound = Vec::new();
+    for path in paths {
+        match bind(path) {
+            Ok(listener) => bound.push(LocalListener {
+                path: path.clone(),
+                listener,
+                owned: true,
+            }),
+            Err(e) => {
+                log::log(
+                    log::LogLevel::Error,
+                    &format!("✗ Failed to bind to unix:{}: {}", path.display(), e),
+                );
+            }
+        }
+    }
+    bound
+}
+
+// Wraps an already listening Unix socket, e.g. one passed in by systemd.
+// Its file belongs to whoever created it and is left in place on exit.
+pub fn adopt(socket: Socket, path: PathBuf) -> io::Result<LocalListener> {
+    socket.set_nonblocking(true)?;
+    let std_listener: std::os::unix::net::UnixListener = OwnedFd::from(socket).into();
+    Ok(LocalListener {
+        path,
+        listener: UnixListener::from_std(std_listener)?,
+        owned: false,
+    })
+}
+
+pub async fn serve(local: LocalListener) {
+    let app = local_router();
+    if let Err(e) = axum::serve(
+        local.listener,
+        app.into_make_service_with_connect_info::<UnixPeer>(),
+    )
+    .with_graceful_shutdown(shutdown::signalled())
+    .await
+    {
+        log::log(
+            log::LogLevel::Error,
+            &format!("✗ Axum server error on unix:{}: {}", local.path.display(), e),
+        );
+    }
+    if local.owned {
+        let _ = fs::remove_file(&local.path);
+    }
+}
+
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if path.exists() {
+        // A socket nobody answers on is left over from an unclean exit.
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                "socket is in use by another process",
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    let dir = path.parent().unwrap_or(Path::new("/"));
+    fs::create_dir_all(dir)?;
+
+    // Bound in a private directory and moved into place once its mode and group are
+    // set, so the socket is never reachable with the permissions the umask gives it.
+    let staging = dir.join(format!(".twig-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("sock");
+    let listener = prepare(&staged).and_then(|listener| fs::rename(&staged, path).map(|_| listener));
+    let _ = fs::remove_dir_all(&staging);
+    listener
+}
+
+// Binds and applies the configured mode and group.
+fn prepare(path: &Path) -> io::Result<UnixListener> {
+    let listener = UnixListener::bind(path)?;
+    let config = env::current();
+    fs::set_permissions(path, fs::Permissions::from_mode(config.unix_socket_mode))?;
+    if let Some(group) = &config.unix_socket_group {
+        let gid = resolve_group(group).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("unknown group '{}'", group))
+        })?;
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        if unsafe { libc::chown(c_path.as_ptr(), u32::MAX, gid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(listener)
+}
+
+fn resolve_group(group: &str) -> Option<libc::gid_t> {
+    if let Ok(gid) = group.parse::<libc::gid_t>() {
+        return Some(gid);
+    }
+    let name = CString::new(group).ok()?;
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut result: *mut libc::group = std::ptr::null_mut();
+    let rc = unsafe { libc::getgrnam_r(name.as_ptr(), &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    Some(grp.gr_gid)
+}
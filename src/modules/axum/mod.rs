@@ -1,3 +1,8 @@
 // src/modules/axum/mod.rs
 
+pub mod connection;
 pub mod core;
+pub mod listener;
+pub mod local;
+#[cfg(feature = "tls")]
+pub mod tls;
@@ -0,0 +1,345 @@
+// src/modules/axum/tls.rs
+
+use crate::common::env;
+use crate::common::log;
+use crate::core::shutdown;
+use crate::middlewares::clientcert::ClientCertificate;
+use crate::modules::axum::connection::{self, is_connection_error, ACCEPT_BACKOFF};
+use axum::{extract::ConnectInfo, Extension, Router};
+use hyper_util::server::graceful::GracefulShutdown;
+use once_cell::sync::{Lazy, OnceCell};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::ssl::{
+    select_next_proto, AlpnError, Ssl, SslAcceptor, SslFiletype, SslMethod, SslVerifyMode,
+};
+use openssl::x509::extension::{
+    BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+};
+use openssl::x509::{X509NameBuilder, X509Name, X509Ref, X509VerifyResult, X509};
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use sysinfo::System;
+use tokio::net::TcpListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_openssl::SslStream;
+
+const TLS_DIR: &str = "/opt/rfs/twig/config/tls";
+const WATCH_INTERVAL: Duration = Duration::from_secs(10);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+// The acceptor is swapped on reload; connections already established keep the old one.
+static ACCEPTOR: Lazy<RwLock<Option<Arc<SslAcceptor>>>> = Lazy::new(|| RwLock::new(None));
+static PATHS: OnceCell<(PathBuf, PathBuf)> = OnceCell::new();
+// tls.client_ca and tls.client_required as read at startup.
+static CLIENT_AUTH: OnceCell<Option<(PathBuf, bool)>> = OnceCell::new();
+
+// Loads the certificate (generating a self-signed one if none is configured)
+// and starts watching it for changes. Safe to call more than once.
+pub fn init() -> Result<(), String> {
+    if PATHS.get().is_some() {
+        return Ok(());
+    }
+
+    let config = env::current();
+    let (cert, key) = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => (PathBuf::from(cert), PathBuf::from(key)),
+        (None, None) => {
+            let cert = Path::new(TLS_DIR).join("cert.pem");
+            let key = Path::new(TLS_DIR).join("key.pem");
+            if !cert.exists() || !key.exists() {
+                generate_self_signed(&cert, &key)
+                    .map_err(|e| format!("failed to generate self-signed certificate: {}", e))?;
+                log::log(
+                    log::LogLevel::Warn,
+                    &format!("▲ Generated self-signed certificate at {}", cert.display()),
+                );
+            }
+            (cert, key)
+        }
+        _ => return Err("tls.cert and tls.key (TLS_CERT, TLS_KEY) must be set together".to_string()),
+    };
+    let client_auth = config
+        .tls_client_ca
+        .as_ref()
+        .map(|ca| (PathBuf::from(ca), config.tls_client_required));
+    if client_auth.is_none() && config.tls_client_required {
+        return Err("tls.client_required needs tls.client_ca (TLS_CLIENT_CA)".to_string());
+    }
+    let _ = CLIENT_AUTH.set(client_auth);
+
+    let acceptor = build_acceptor(&cert, &key)
+        .map_err(|e| format!("failed to load certificate {}: {}", cert.display(), e))?;
+    *ACCEPTOR.write().unwrap() = Some(Arc::new(acceptor));
+    log_certificate(&cert, "✓ TLS certificate loaded");
+    if let Some(Some((ca, required))) = CLIENT_AUTH.get() {
+        let mode = if *required { "required" } else { "optional" };
+        log::log(
+            log::LogLevel::Info,
+            &format!("✓ Client certificates {}, issued by {}", mode, ca.display()),
+        );
+    }
+
+    let _ = PATHS.set((cert, key));
+    spawn_reload_watcher();
+    Ok(())
+}
+
+// Accepts connections, performs the TLS handshake and serves the router over it.
+// On shutdown, accepting stops and open connections are asked to close gracefully.
+pub async fn serve(listener: TcpListener, app: Router) {
+    let graceful = GracefulShutdown::new();
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown::signalled() => break,
+        };
+        let (stream, peer) = match accepted {
+            Ok(conn) => conn,
+            Err(e) if is_connection_error(&e) => {
+                log::log(log::LogLevel::Debug, &format!("▪ TLS accept error: {}", e));
+                continue;
+            }
+            // Out of file descriptors and the like: retrying at once would spin.
+            Err(e) => {
+                log::log(log::LogLevel::Warn, &format!("▲ TLS accept error, pausing {}s: {}", ACCEPT_BACKOFF.as_secs(), e));
+                tokio::select! {
+                    _ = tokio::time::sleep(ACCEPT_BACKOFF) => continue,
+                    _ = shutdown::signalled() => break,
+                }
+            }
+        };
+        let Some(acceptor) = ACCEPTOR.read().unwrap().clone() else {
+            continue;
+        };
+        let app = app.clone();
+        let watcher = graceful.watcher();
+
+        tokio::spawn(async move {
+            let tls_stream = match tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake(&acceptor, stream)).await {
+                Ok(Ok(tls_stream)) => tls_stream,
+                Ok(Err(e)) => {
+                    log::log(log::LogLevel::Debug, &format!("▪ TLS handshake with {} failed: {}", peer, e));
+                    return;
+                }
+                Err(_) => {
+                    log::log(log::LogLevel::Debug, &format!("▪ TLS handshake with {} timed out", peer));
+                    return;
+                }
+            };
+
+            // A verified client certificate authenticates every request on the connection.
+            let app = match client_certificate(&tls_stream) {
+                Some(cert) => {
+                    log::log(
+                        log::LogLevel::Debug,
+                        &format!("➜ {} presented client certificate {}", peer, cert.subject),
+                    );
+                    app.layer(Extension(cert))
+                }
+                None => app,
+            };
+            // Expose the peer address to handlers the same way plain HTTP does.
+            let app = app.layer(Extension(ConnectInfo::<SocketAddr>(peer)));
+            connection::serve(tls_stream, app, watcher).await;
+        });
+    }
+    graceful.shutdown().await;
+}
+
+async fn handshake(
+    acceptor: &SslAcceptor,
+    stream: tokio::net::TcpStream,
+) -> Result<SslStream<tokio::net::TcpStream>, BoxError> {
+    let ssl = Ssl::new(acceptor.context())?;
+    let mut tls_stream = SslStream::new(ssl, stream)?;
+    Pin::new(&mut tls_stream).accept().await?;
+    Ok(tls_stream)
+}
+
+fn client_certificate(stream: &SslStream<tokio::net::TcpStream>) -> Option<ClientCertificate> {
+    let ssl = stream.ssl();
+    if ssl.verify_result() != X509VerifyResult::OK {
+        return None;
+    }
+    let cert = ssl.peer_certificate()?;
+    Some(ClientCertificate {
+        common_name: cert
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .map(|entry| String::from_utf8_lossy(entry.data().as_slice()).into_owned()),
+        subject: one_line_subject(&cert),
+        fingerprint: cert
+            .digest(MessageDigest::sha256())
+            .map(|digest| digest.iter().map(|b| format!("{:02x}", b)).collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn one_line_subject(cert: &X509Ref) -> String {
+    cert.subject_name()
+        .entries()
+        .filter_map(|entry| {
+            let key = entry.object().nid().short_name().ok()?;
+            Some(format!("{}={}", key, String::from_utf8_lossy(entry.data().as_slice())))
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn build_acceptor(cert: &Path, key: &Path) -> Result<SslAcceptor, BoxError> {
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server())?;
+    builder.set_private_key_file(key, SslFiletype::PEM)?;
+    builder.set_certificate_chain_file(cert)?;
+    builder.check_private_key()?;
+    if let Some(Some((ca, required))) = CLIENT_AUTH.get() {
+        // Certificates not issued by the CA fail the handshake; without `required`
+        // a client may send none and authenticate with a token instead.
+        builder.set_ca_file(ca)?;
+        builder.set_client_ca_list(X509Name::load_client_ca_file(ca)?);
+        let mut mode = SslVerifyMode::PEER;
+        if *required {
+            mode |= SslVerifyMode::FAIL_IF_NO_PEER_CERT;
+        }
+        builder.set_verify(mode);
+        // Needed for session resumption once peers are verified.
+        builder.set_session_id_context(b"twig")?;
+    }
+    builder.set_alpn_select_callback(|_, client| {
+        select_next_proto(b"\x02h2\x08http/1.1", client).ok_or(AlpnError::NOACK)
+    });
+    Ok(builder.build())
+}
+
+// Reloads on SIGHUP or when the certificate, key or client CA file changes.
+fn spawn_reload_watcher() {
+    tokio::spawn(async move {
+        let Some((cert, key)) = PATHS.get() else { return };
+        let ca = CLIENT_AUTH.get().cloned().flatten().map(|(ca, _)| ca);
+        let ca_modified = || ca.as_deref().and_then(modified);
+        let mut last_seen = (modified(cert), modified(key), ca_modified());
+        let mut hangup = signal(SignalKind::hangup()).ok();
+        let mut ticker = tokio::time::interval(WATCH_INTERVAL);
+
+        loop {
+            let by_signal = tokio::select! {
+                _ = ticker.tick() => false,
+                Some(_) = async {
+                    match hangup.as_mut() {
+                        Some(stream) => stream.recv().await,
+                        None => std::future::pending().await,
+                    }
+                } => true,
+            };
+
+            let current = (modified(cert), modified(key), ca_modified());
+            if !by_signal && current == last_seen {
+                continue;
+            }
+            last_seen = current;
+            reload(cert, key);
+        }
+    });
+}
+
+fn reload(cert: &Path, key: &Path) {
+    match build_acceptor(cert, key) {
+        Ok(acceptor) => {
+            *ACCEPTOR.write().unwrap() = Some(Arc::new(acceptor));
+            log_certificate(cert, "➜ TLS certificate reloaded");
+        }
+        Err(e) => {
+            log::log(
+                log::LogLevel::Error,
+                &format!("✗ Failed to reload TLS certificate, keeping previous: {}", e),
+            );
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn log_certificate(path: &Path, prefix: &str) {
+    let expires = fs::read(path)
+        .ok()
+        .and_then(|pem| X509::from_pem(&pem).ok())
+        .map(|cert| cert.not_after().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    log::log(
+        log::LogLevel::Info,
+        &format!("{} {} (expires {})", prefix, path.display(), expires),
+    );
+}
+
+fn generate_self_signed(cert_path: &Path, key_path: &Path) -> Result<(), BoxError> {
+    let host = System::host_name().unwrap_or_else(|| "twig".to_string());
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, &host)?;
+    name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "rfs twig")?;
+    let name = name.build();
+
+    let mut serial = BigNum::new()?;
+    serial.rand(128, MsbOption::MAYBE_ZERO, false)?;
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    let serial = serial.to_asn1_integer()?;
+    let not_before = Asn1Time::days_from_now(0)?;
+    let not_after = Asn1Time::days_from_now(825)?;
+    builder.set_serial_number(&serial)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&key)?;
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+
+    // Cover every name a client on the local network is likely to use.
+    let mut san = SubjectAlternativeName::new();
+    san.dns("localhost").dns(&host).ip("127.0.0.1").ip("::1");
+    if let Ok(interfaces) = get_if_addrs::get_if_addrs() {
+        for iface in interfaces.iter().filter(|i| !i.addr.ip().is_loopback()) {
+            san.ip(&iface.addr.ip().to_string());
+        }
+    }
+    let san = san.build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(san)?;
+    builder.append_extension(BasicConstraints::new().critical().build()?)?;
+    builder.append_extension(KeyUsage::new().critical().digital_signature().build()?)?;
+    builder.append_extension(ExtendedKeyUsage::new().server_auth().build()?)?;
+    builder.sign(&key, MessageDigest::sha256())?;
+    let cert = builder.build();
+
+    fs::create_dir_all(TLS_DIR)?;
+    write_private(key_path, &key.private_key_to_pem_pkcs8()?)?;
+    write_private(cert_path, &cert.to_pem()?)?;
+    Ok(())
+}
+
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(data)
+}
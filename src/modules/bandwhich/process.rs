@@ -1,5 +1,6 @@
 /* src/modules/bandwhich/process.rs */
 
+use crate::core::{collector, shutdown};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::Serialize;
@@ -24,6 +25,8 @@ static PROCESS_CACHE: Lazy<ProcessCache> = Lazy::new(|| Arc::new(Mutex::new(Vec:
 static LAST_ACCESS: Lazy<Arc<Mutex<Instant>>> = Lazy::new(|| Arc::new(Mutex::new(Instant::now())));
 // A flag to ensure only one instance of the listener thread is running.
 static IS_RUNNING: Lazy<Arc<Mutex<bool>>> = Lazy::new(|| Arc::new(Mutex::new(false)));
+// PID of the running `bandwhich` child, so shutdown can terminate it.
+static CHILD_PID: Lazy<Mutex<Option<u32>>> = Lazy::new(|| Mutex::new(None));
 
 /// Parses a single line of `bandwhich --raw` output.
 fn parse_line(line: &str) -> Option<ProcessInfo> {
@@ -53,7 +56,9 @@ fn start_bandwhich_listener() {
     thread::spawn(move || {
         // This outer loop manages the 60-second inactivity timeout.
         loop {
-            if last_access.lock().unwrap().elapsed() > Duration::from_secs(60) {
+            if last_access.lock().unwrap().elapsed() > Duration::from_secs(60)
+                || shutdown::is_shutting_down()
+            {
                 //println!("bandwhich listener timing out due to inactivity.");
                 *cache.lock().unwrap() = Vec::new(); // Clear cache on exit.
                 *running_flag.lock().unwrap() = false;
@@ -73,6 +78,7 @@ fn start_bandwhich_listener() {
                     return;
                 }
             };
+            *CHILD_PID.lock().unwrap() = Some(child.id());
 
             if let Some(stdout) = child.stdout.take() {
                 let reader = BufReader::new(stdout);
@@ -112,12 +118,27 @@ fn start_bandwhich_listener() {
             // Kill the current child process before restarting in the next outer loop iteration.
             let _ = child.kill();
             let _ = child.wait(); // Clean up the zombie process.
+            *CHILD_PID.lock().unwrap() = None;
         }
     });
 }
 
+/// Terminates the running `bandwhich` child; the listener thread then exits on its own.
+pub fn stop() {
+    if let Some(pid) = CHILD_PID.lock().unwrap().take() {
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+    }
+}
+
 /// Gets the list of processes and their network usage from `bandwhich`.
 pub fn get_bandwhich_process() -> Vec<ProcessInfo> {
+    // Without packet capture rights bandwhich exits immediately; don't keep respawning it.
+    if !collector::is_available("network-processes") {
+        return Vec::new();
+    }
+
     // Update the last access time on every call.
     *LAST_ACCESS.lock().unwrap() = Instant::now();
 
@@ -98,14 +98,13 @@ async fn read_intel_rapl_power() -> Result<f64, String> {
             if path.is_dir() {
                 let energy_uj_path = path.join("energy_uj");
                 let max_energy_uj_path = path.join("max_energy_range_uj");
-                if energy_uj_path.exists() && max_energy_uj_path.exists() {
-                    if let Ok(energy_str) = fs::read_to_string(&energy_uj_path) {
-                        if let Ok(energy_uj) = energy_str.trim().parse::<u64>() {
-                            let power_watts = energy_uj as f64 / 1_000_000.0 / 1000.0;
-                            total_power += power_watts;
-                            found_any = true;
-                        }
-                    }
+                if energy_uj_path.exists() && max_energy_uj_path.exists()
+                    && let Ok(energy_str) = fs::read_to_string(&energy_uj_path)
+                    && let Ok(energy_uj) = energy_str.trim().parse::<u64>()
+                {
+                    let power_watts = energy_uj as f64 / 1_000_000.0 / 1000.0;
+                    total_power += power_watts;
+                    found_any = true;
                 }
             }
         }
@@ -140,13 +139,13 @@ async fn read_amd_hwmon_power() -> Result<f64, String> {
                         let filename_str = filename.to_string_lossy();
                         if filename_str.starts_with("power") && filename_str.ends_with("_input") {
                             let power_file = power_entry.path();
-                            if let Ok(power_str) = fs::read_to_string(&power_file) {
-                                if let Ok(power_microwatts) = power_str.trim().parse::<u64>() {
-                                    // mW -> W
-                                    let power_watts = power_microwatts as f64 / 1_000_000.0;
-                                    total_power += power_watts;
-                                    found_any = true;
-                                }
+                            if let Ok(power_str) = fs::read_to_string(&power_file)
+                                && let Ok(power_microwatts) = power_str.trim().parse::<u64>()
+                            {
+                                // mW -> W
+                                let power_watts = power_microwatts as f64 / 1_000_000.0;
+                                total_power += power_watts;
+                                found_any = true;
                             }
                         }
                     }
@@ -181,13 +180,13 @@ async fn read_arm_iio_power() -> Result<f64, String> {
                         let filename_str = filename.to_string_lossy();
                         if filename_str.contains("iio") && filename_str.contains("input") {
                             let iio_file = iio_entry.path();
-                            if let Ok(power_str) = fs::read_to_string(&iio_file) {
-                                if let Ok(power_value) = power_str.trim().parse::<u64>() {
-                                    // ARM IIO
-                                    let power_watts = power_value as f64 / 1_000_000.0;
-                                    total_power += power_watts;
-                                    found_any = true;
-                                }
+                            if let Ok(power_str) = fs::read_to_string(&iio_file)
+                                && let Ok(power_value) = power_str.trim().parse::<u64>()
+                            {
+                                // ARM IIO
+                                let power_watts = power_value as f64 / 1_000_000.0;
+                                total_power += power_watts;
+                                found_any = true;
                             }
                         }
                     }
@@ -1,6 +1,7 @@
 // src/modules/docker/operations.rs
 
 use crate::core::response;
+use crate::middlewares::audit;
 use crate::modules::docker::unix;
 use axum::{extract::Path, http::{Method, StatusCode}, response::Response};
 use http_body_util::BodyExt;
@@ -16,11 +17,11 @@ async fn handle_container_post_action(id: String, action: &str) -> Response {
                 Ok(body) => body.to_bytes(),
                 Err(e) => {
                     eprintln!("Failed to read Docker response body: {}", e);
-                    return response::internal_error();
+                    return audit::annotate(response::internal_error(), &id, Some(status.as_u16()));
                 }
             };
 
-            if status.is_success() {
+            let response = if status.is_success() {
                 // For actions like start/stop, Docker often returns 204 No Content.
                 // If there is a body, we attempt to parse and forward it.
                 if body_bytes.is_empty() {
@@ -42,11 +43,12 @@ async fn handle_container_post_action(id: String, action: &str) -> Response {
                         response::error(status, format!("Docker API error: {}", error_message))
                     }
                 }
-            }
+            };
+            audit::annotate(response, &id, Some(status.as_u16()))
         }
         Err(e) => {
             eprintln!("Failed to send request to Docker: {}", e);
-            response::service_unavailable()
+            audit::annotate(response::service_unavailable(), &id, None)
         },
     }
 }
@@ -95,20 +97,20 @@ pub async fn delete_container_handler(Path(id): Path<String>) -> Response {
     match unix::send_request(Method::DELETE, &path).await {
         Ok(res) => {
             let status = res.status();
-            if status == StatusCode::NO_CONTENT {
+            let response = if status == StatusCode::NO_CONTENT {
                 // Successfully deleted
                 response::success(None)
             } else {
-                let body_bytes = match res.collect().await {
-                    Ok(body) => body.to_bytes(),
-                    Err(_) => return response::internal_error(),
-                };
-                match serde_json::from_slice::<Value>(&body_bytes) {
-                    Ok(json_error) => response::error(status, json_error.to_string()),
-                    Err(_) => response::error(status, "Failed to delete container."),
+                match res.collect().await {
+                    Ok(body) => match serde_json::from_slice::<Value>(&body.to_bytes()) {
+                        Ok(json_error) => response::error(status, json_error.to_string()),
+                        Err(_) => response::error(status, "Failed to delete container."),
+                    },
+                    Err(_) => response::internal_error(),
                 }
-            }
+            };
+            audit::annotate(response, &id, Some(status.as_u16()))
         }
-        Err(_) => response::service_unavailable(),
+        Err(_) => audit::annotate(response::service_unavailable(), &id, None),
     }
 }
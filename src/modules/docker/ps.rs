@@ -11,7 +11,7 @@ pub fn is_docker_installed() -> bool {
     Command::new("which")
         .arg("docker")
         .status()
-        .map_or(false, |status| status.success())
+        .is_ok_and(|status| status.success())
 }
 
 // check if docker daemon is running via unix socket ping
@@ -6,14 +6,13 @@ use hyper::{body::{Bytes, Incoming}, client::conn::http1, Method, Request, Respo
 use tokio::net::UnixStream;
 use http_body_util::{BodyExt, Empty};
 use hyper_util::rt::TokioIo;
-
-const DOCKER_SOCKET_PATH: &str = "/var/run/docker.sock";
+use crate::common::env;
 
 pub async fn send_request(
     method: Method,
     path: &str,
 ) -> Result<Response<Incoming>, Box<dyn Error + Send + Sync>> {
-    let stream = UnixStream::connect(DOCKER_SOCKET_PATH).await?;
+    let stream = UnixStream::connect(&env::current().docker_socket).await?;
     let io = TokioIo::new(stream);
     let (mut sender, conn) = http1::handshake(io).await?;
 
@@ -24,10 +24,10 @@ fn parse_docker_version_output(output: &str) -> Value {
             section_name = Some(name.clone());
             subsection_name = None; // Reset subsection on new section
 
-            if name == "server" && parts.len() > 1 && !parts[1].trim().is_empty() {
-                if let Some(obj) = result["server"].as_object_mut() {
-                    obj.insert("title".to_string(), json!(parts[1].trim()));
-                }
+            if name == "server" && parts.len() > 1 && !parts[1].trim().is_empty()
+                && let Some(obj) = result["server"].as_object_mut()
+            {
+                obj.insert("title".to_string(), json!(parts[1].trim()));
             }
         } else {
             // This is an indented line, either a subsection header or a key-value pair.
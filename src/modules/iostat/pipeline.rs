@@ -1,5 +1,6 @@
 /* src/modules/iostat/pipeline.rs */
 
+use crate::core::{collector, shutdown};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -37,6 +38,10 @@ lazy_static! {
 // This function is platform-agnostic in its caching but uses platform-specific
 // commands and parsers internally.
 pub async fn fetch_iostat() -> IostatCache {
+    if !collector::is_available("storage-io") {
+        return None;
+    }
+
     {
         // Update last access time on every call.
         let mut last_access = LAST_ACCESS.lock().unwrap();
@@ -66,7 +71,7 @@ pub async fn fetch_iostat() -> IostatCache {
                 // Check if the cache is still needed.
                 {
                     let last = last_access_clone.lock().unwrap();
-                    if last.elapsed() > Duration::from_secs(60) {
+                    if last.elapsed() > Duration::from_secs(60) || shutdown::is_shutting_down() {
                         *cache_clone.lock().unwrap() = None;
                         break; // Stop the task.
                     }
@@ -84,12 +89,11 @@ pub async fn fetch_iostat() -> IostatCache {
                     .stderr(Stdio::null())
                     .output()
                     .await
+                    && let Ok(stdout) = String::from_utf8(output.stdout)
                 {
-                    if let Ok(stdout) = String::from_utf8(output.stdout) {
-                        // The parser is now platform-specific.
-                        if let Some(parsed_data) = parse_iostat_output(&stdout) {
-                            *cache_clone.lock().unwrap() = Some(parsed_data);
-                        }
+                    // The parser is now platform-specific.
+                    if let Some(parsed_data) = parse_iostat_output(&stdout) {
+                        *cache_clone.lock().unwrap() = Some(parsed_data);
                     }
                 }
             }
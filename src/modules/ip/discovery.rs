@@ -0,0 +1,347 @@
+// src/modules/ip/discovery.rs
+
+use crate::common::{env, log};
+use chrono::{DateTime, Utc};
+use futures::future;
+use http_body_util::{BodyExt, Empty, Limited};
+use hyper::body::Bytes;
+use hyper_util::rt::TokioIo;
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
+use tokio::time::timeout;
+
+// RFC 5389 constants.
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_SUCCESS: u16 = 0x0101;
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+// Plain-text providers answer with the address and a newline; anything longer is not one.
+const MAX_HTTP_BODY: usize = 1024;
+
+#[derive(Clone)]
+pub struct PublicAddresses {
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6: Option<Ipv6Addr>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PublicAddresses {
+    // IPv4 when known, since that is what most clients can reach.
+    pub fn primary(&self) -> Option<IpAddr> {
+        self.ipv4.map(IpAddr::V4).or(self.ipv6.map(IpAddr::V6))
+    }
+}
+
+static LAST: Lazy<Mutex<Option<PublicAddresses>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn is_enabled() -> bool {
+    env::current().discovery_enabled
+}
+
+// Returns the cached addresses while they are younger than `cache.ip_ttl`,
+// otherwise asks the configured providers again.
+pub async fn public_addresses() -> Result<PublicAddresses, String> {
+    let ttl = chrono::Duration::from_std(env::current().ip_cache_ttl).unwrap_or_default();
+    if let Some(cached) = LAST.lock().unwrap().as_ref()
+        && Utc::now() - cached.updated_at < ttl
+    {
+        return Ok(cached.clone());
+    }
+    let found = discover().await?;
+    *LAST.lock().unwrap() = Some(found.clone());
+    Ok(found)
+}
+
+// Queries every STUN server over both address families, then falls back to the
+// HTTP providers for whichever family is still unknown.
+pub async fn discover() -> Result<PublicAddresses, String> {
+    let config = env::current();
+    if !config.discovery_enabled {
+        return Err("public address discovery is disabled".to_string());
+    }
+    let limit = config.discovery_timeout;
+
+    let mut found: Vec<IpAddr> = Vec::new();
+    let stun = config.discovery_stun.iter().map(|server| stun_query(server, limit));
+    for result in future::join_all(stun).await {
+        match result {
+            Ok(addrs) => found.extend(addrs),
+            Err(e) => log::log(log::LogLevel::Debug, &format!("▪ STUN: {}", e)),
+        }
+    }
+
+    let mut addresses = collect(&found);
+    if addresses.ipv4.is_none() || addresses.ipv6.is_none() {
+        let http = config.discovery_http.iter().map(|url| http_query(url, limit));
+        for result in future::join_all(http).await {
+            match result {
+                Ok(addrs) => found.extend(addrs),
+                Err(e) => log::log(log::LogLevel::Debug, &format!("▪ HTTP discovery: {}", e)),
+            }
+        }
+        addresses = collect(&found);
+    }
+
+    if addresses.primary().is_none() {
+        return Err("no discovery provider answered".to_string());
+    }
+    Ok(addresses)
+}
+
+// First public answer per family, in provider order.
+fn collect(found: &[IpAddr]) -> PublicAddresses {
+    PublicAddresses {
+        ipv4: found.iter().find_map(|ip| match ip {
+            IpAddr::V4(v4) => Some(*v4),
+            _ => None,
+        }),
+        ipv6: found.iter().find_map(|ip| match ip {
+            IpAddr::V6(v6) => Some(*v6),
+            _ => None,
+        }),
+        updated_at: Utc::now(),
+    }
+}
+
+// Resolves a `host:port` and keeps one address per family, so a dual-stack
+// provider reports both public addresses.
+async fn resolve_per_family(target: &str) -> Result<Vec<SocketAddr>, String> {
+    let resolved: Vec<SocketAddr> = lookup_host(target)
+        .await
+        .map_err(|e| format!("{}: {}", target, e))?
+        .collect();
+    let v4 = resolved.iter().find(|a| a.is_ipv4()).copied();
+    let v6 = resolved.iter().find(|a| a.is_ipv6()).copied();
+    Ok(v4.into_iter().chain(v6).collect())
+}
+
+async fn stun_query(server: &str, limit: Duration) -> Result<Vec<IpAddr>, String> {
+    let targets = timeout(limit, resolve_per_family(server))
+        .await
+        .map_err(|_| format!("{}: resolve timed out", server))??;
+    let queries = targets.into_iter().map(|target| async move {
+        timeout(limit, stun_binding(target))
+            .await
+            .map_err(|_| format!("{}: timed out", target))?
+    });
+
+    let mut addrs = Vec::new();
+    let mut errors = Vec::new();
+    for result in future::join_all(queries).await {
+        match result {
+            Ok(ip) => addrs.push(ip),
+            Err(e) => errors.push(e),
+        }
+    }
+    if addrs.is_empty() {
+        Err(format!("{}: {}", server, errors.join(", ")))
+    } else {
+        Ok(addrs)
+    }
+}
+
+// Sends one Binding request and returns the reflexive address from the response.
+async fn stun_binding(target: SocketAddr) -> Result<IpAddr, String> {
+    let bind: SocketAddr = if target.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind).await.map_err(|e| format!("{}: {}", target, e))?;
+    socket.connect(target).await.map_err(|e| format!("{}: {}", target, e))?;
+
+    let mut transaction = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut transaction);
+    let mut request = Vec::with_capacity(20);
+    request.extend_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(&transaction);
+    socket.send(&request).await.map_err(|e| format!("{}: {}", target, e))?;
+
+    let mut buf = [0u8; 512];
+    // Stray datagrams are ignored until the matching response arrives or the caller times out.
+    loop {
+        let len = socket.recv(&mut buf).await.map_err(|e| format!("{}: {}", target, e))?;
+        if let Some(ip) = parse_binding_response(&buf[..len], &transaction) {
+            return Ok(ip);
+        }
+    }
+}
+
+fn parse_binding_response(packet: &[u8], transaction: &[u8; 12]) -> Option<IpAddr> {
+    if packet.len() < 20
+        || u16::from_be_bytes([packet[0], packet[1]]) != STUN_BINDING_SUCCESS
+        || u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]) != STUN_MAGIC_COOKIE
+        || &packet[8..20] != transaction
+    {
+        return None;
+    }
+    let length = u16::from_be_bytes([packet[2], packet[3]]) as usize;
+    let attributes = packet.get(20..20 + length)?;
+
+    let mut mapped = None;
+    let mut offset = 0;
+    while offset + 4 <= attributes.len() {
+        let kind = u16::from_be_bytes([attributes[offset], attributes[offset + 1]]);
+        let size = u16::from_be_bytes([attributes[offset + 2], attributes[offset + 3]]) as usize;
+        let value = attributes.get(offset + 4..offset + 4 + size)?;
+        match kind {
+            ATTR_XOR_MAPPED_ADDRESS => return parse_address(value, Some(transaction)),
+            ATTR_MAPPED_ADDRESS => mapped = parse_address(value, None),
+            _ => {}
+        }
+        // Attributes are padded to a multiple of four bytes.
+        offset += 4 + size.div_ceil(4) * 4;
+    }
+    mapped
+}
+
+// (XOR-)MAPPED-ADDRESS: reserved byte, family, port, address.
+fn parse_address(value: &[u8], xor_with: Option<&[u8; 12]>) -> Option<IpAddr> {
+    let mut mask = [0u8; 16];
+    if let Some(transaction) = xor_with {
+        mask[..4].copy_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+        mask[4..].copy_from_slice(transaction);
+    }
+    match value.get(1)? {
+        0x01 => {
+            let raw = value.get(4..8)?;
+            let octets: [u8; 4] = std::array::from_fn(|i| raw[i] ^ mask[i]);
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        0x02 => {
+            let raw = value.get(4..20)?;
+            let octets: [u8; 16] = std::array::from_fn(|i| raw[i] ^ mask[i]);
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+// GETs a plain-text "what is my IP" endpoint once per address family.
+async fn http_query(url: &str, limit: Duration) -> Result<Vec<IpAddr>, String> {
+    let (tls, host, port, path) = parse_url(url)?;
+    let targets = timeout(limit, resolve_per_family(&format!("{}:{}", host, port)))
+        .await
+        .map_err(|_| format!("{}: resolve timed out", url))??;
+
+    let queries = targets.into_iter().map(|target| {
+        let host = host.clone();
+        let path = path.clone();
+        async move {
+            timeout(limit, http_get(target, tls, &host, &path))
+                .await
+                .map_err(|_| format!("{} via {}: timed out", url, target))?
+                .map_err(|e| format!("{} via {}: {}", url, target, e))
+        }
+    });
+
+    let mut addrs = Vec::new();
+    let mut errors = Vec::new();
+    for result in future::join_all(queries).await {
+        match result {
+            Ok(ip) => addrs.push(ip),
+            Err(e) => errors.push(e),
+        }
+    }
+    if addrs.is_empty() {
+        Err(errors.join(", "))
+    } else {
+        Ok(addrs)
+    }
+}
+
+// Splits `http(s)://host[:port][/path]`. Also used to validate the config.
+pub fn parse_url(url: &str) -> Result<(bool, String, u16, String), String> {
+    let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (false, rest)
+    } else {
+        return Err(format!("expected an http:// or https:// URL, got '{}'", url));
+    };
+    if tls && !cfg!(feature = "tls") {
+        return Err(format!("'{}' needs a build with the tls feature", url));
+    }
+
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], rest[index..].to_string()),
+        None => (rest, "/".to_string()),
+    };
+    let default_port = if tls { 443 } else { 80 };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (
+            host,
+            port.parse::<u16>().map_err(|_| format!("invalid port in '{}'", url))?,
+        ),
+        _ => (authority, default_port),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(format!("missing host in '{}'", url));
+    }
+    Ok((tls, host.to_string(), port, path))
+}
+
+async fn http_get(target: SocketAddr, tls: bool, host: &str, path: &str) -> Result<IpAddr, String> {
+    let tcp = TcpStream::connect(target).await.map_err(|e| e.to_string())?;
+
+    #[cfg(feature = "tls")]
+    if tls {
+        use openssl::ssl::{SslConnector, SslMethod};
+        use std::pin::Pin;
+        use tokio_openssl::SslStream;
+
+        let connector = SslConnector::builder(SslMethod::tls_client())
+            .map_err(|e| e.to_string())?
+            .build();
+        let ssl = connector
+            .configure()
+            .and_then(|c| c.into_ssl(host))
+            .map_err(|e| e.to_string())?;
+        let mut stream = SslStream::new(ssl, tcp).map_err(|e| e.to_string())?;
+        Pin::new(&mut stream).connect().await.map_err(|e| e.to_string())?;
+        return request(stream, host, path).await;
+    }
+    #[cfg(not(feature = "tls"))]
+    let _ = tls;
+
+    request(tcp, host, path).await
+}
+
+async fn request<S>(stream: S, host: &str, path: &str) -> Result<IpAddr, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::spawn(connection);
+
+    let req = hyper::Request::get(path)
+        .header(hyper::header::HOST, host)
+        .header(hyper::header::USER_AGENT, concat!("twig/", env!("CARGO_PKG_VERSION")))
+        .body(Empty::<Bytes>::new())
+        .map_err(|e| e.to_string())?;
+    let res = sender.send_request(req).await.map_err(|e| e.to_string())?;
+    if !res.status().is_success() {
+        return Err(format!("status {}", res.status()));
+    }
+    let body = Limited::new(res.into_body(), MAX_HTTP_BODY)
+        .collect()
+        .await
+        .map_err(|e| e.to_string())?
+        .to_bytes();
+    let text = String::from_utf8_lossy(&body);
+    text.trim()
+        .parse::<IpAddr>()
+        .map_err(|_| format!("not an IP address: '{}'", text.trim()))
+}
@@ -3,75 +3,44 @@
 use axum::response::Response;
 use chrono::{DateTime, Duration, SecondsFormat, Utc};
 use futures::future;
-use ip_lookup::{get_public_ip_addr, lookup, LookupProvider, LookupResult};
+use ip_lookup::{lookup, LookupProvider, LookupResult};
 use once_cell::sync::Lazy;
 use rand::seq::SliceRandom;
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::sync::Mutex;
 use tokio::time::timeout;
+use crate::common::env;
 use crate::core::response;
-
-struct CachedIp {
-    ip: String,
-    updated_at: DateTime<Utc>,
-}
+use crate::modules::ip::discovery;
 
 struct CachedGeoIp {
     data: Value,
     updated_at: DateTime<Utc>,
 }
 
-static LAST_IP: Lazy<Mutex<Option<CachedIp>>> = Lazy::new(|| Mutex::new(None));
 static LAST_GEOIP: Lazy<Mutex<Option<CachedGeoIp>>> = Lazy::new(|| Mutex::new(None));
 
+// Public addresses as seen by the configured STUN and HTTP providers.
 pub async fn get_ip_handler() -> Response {
-    let now = Utc::now();
-    let use_cached = {
-        let guard = LAST_IP.lock().unwrap();
-        guard.as_ref().map_or(false, |cached| now - cached.updated_at < Duration::minutes(15))
-    };
-
-    if use_cached {
-        let guard = LAST_IP.lock().unwrap();
-        if let Some(cached) = &*guard {
-            return response::success(Some(json!({
-                "ip": cached.ip,
-                "update": cached.updated_at.to_rfc3339_opts(SecondsFormat::Secs, true)
-            })));
-        }
-    }
-
-    let result = tokio::task::spawn_blocking(move || get_public_ip_addr())
-        .await
-        .unwrap_or(None);
-
-    match result {
-        Some(ip) => {
-            let updated_at = Utc::now();
-            let mut guard = LAST_IP.lock().unwrap();
-            *guard = Some(CachedIp {
-                ip: ip.clone(),
-                updated_at,
-            });
-
-            response::success(Some(json!({
-                "ip": ip,
-                "update": updated_at.to_rfc3339_opts(SecondsFormat::Secs, true)
-            })))
-        }
-        None => response::service_unavailable(),
+    match discovery::public_addresses().await {
+        Ok(found) => response::success(Some(json!({
+            "ip": found.primary().map(|ip| ip.to_string()),
+            "ipv4": found.ipv4.map(|ip| ip.to_string()),
+            "ipv6": found.ipv6.map(|ip| ip.to_string()),
+            "update": found.updated_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        }))),
+        Err(_) => response::service_unavailable(),
     }
 }
 
 pub async fn get_geoip_handler() -> Response {
     let now = Utc::now();
+    let ttl = Duration::from_std(env::current().geoip_cache_ttl).unwrap_or_default();
     {
         let guard = LAST_GEOIP.lock().unwrap();
-        if let Some(cached) = &*guard {
-            if now - cached.updated_at < Duration::minutes(15) {
-                return response::success(Some(cached.data.clone()));
-            }
+        if let Some(cached) = &*guard && now - cached.updated_at < ttl {
+            return response::success(Some(cached.data.clone()));
         }
     }
 
@@ -95,10 +64,10 @@ pub async fn get_geoip_handler() -> Response {
         })
     });
 
-    let results = match timeout(std::time::Duration::from_secs(10), future::join_all(lookups_as_futures)).await {
-        Ok(res) => res,
-        Err(_) => vec![], // On timeout, we might have no results.
-    };
+    // On timeout, we might have no results.
+    let results = timeout(std::time::Duration::from_secs(10), future::join_all(lookups_as_futures))
+        .await
+        .unwrap_or_default();
 
     let successful_lookups: Vec<LookupResult> = results.into_iter().filter_map(Result::ok).collect();
     if successful_lookups.is_empty() {
@@ -129,13 +98,11 @@ pub async fn get_geoip_handler() -> Response {
 // Main optimization dispatcher. Now operates on a mutable Value.
 fn run_optimized_result(data: &mut Value) {
     // Process Country fields
-    if let Some(country) = data.get_mut("country") {
-        if let Some(map) = country.as_object_mut() {
-            for (key, val) in map.iter_mut() {
-                match key.as_str() {
-                    "city" | "zip" => deduplicate_and_sort_by_frequency(val),
-                    _ => consolidate_to_most_frequent(val),
-                }
+    if let Some(country) = data.get_mut("country") && let Some(map) = country.as_object_mut() {
+        for (key, val) in map.iter_mut() {
+            match key.as_str() {
+                "city" | "zip" => deduplicate_and_sort_by_frequency(val),
+                _ => consolidate_to_most_frequent(val),
             }
         }
     }
@@ -147,11 +114,9 @@ fn run_optimized_result(data: &mut Value) {
 
     // Process all other top-level objects with the standard consolidation rule.
     for key in ["network", "connection"] {
-        if let Some(obj) = data.get_mut(key) {
-            if let Some(obj_map) = obj.as_object_mut() {
-                for (_, field_val) in obj_map.iter_mut() {
-                    consolidate_to_most_frequent(field_val);
-                }
+        if let Some(obj) = data.get_mut(key) && let Some(obj_map) = obj.as_object_mut() {
+            for (_, field_val) in obj_map.iter_mut() {
+                consolidate_to_most_frequent(field_val);
             }
         }
     }
@@ -185,10 +150,8 @@ fn deduplicate_and_sort_by_frequency(field: &mut Value) {
         }
         let mut counts = HashMap::new();
         for val in arr {
-            if let Some(s) = val.as_str() {
-                if s.trim().is_empty() {
-                    continue;
-                }
+            if let Some(s) = val.as_str() && s.trim().is_empty() {
+                continue;
             }
             *counts.entry(val.clone()).or_insert(0) += 1;
         }
@@ -197,7 +160,7 @@ fn deduplicate_and_sort_by_frequency(field: &mut Value) {
             return;
         }
         let mut sorted_unique_vals: Vec<_> = counts.into_iter().collect();
-        sorted_unique_vals.sort_by(|a, b| b.1.cmp(&a.1));
+        sorted_unique_vals.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
         *field = json!(sorted_unique_vals.into_iter().map(|(val, _)| val).collect::<Vec<_>>());
     }
 }
@@ -264,10 +227,8 @@ fn initial_data_from_response(res: &LookupResult) -> Value {
 
 fn merge_data(cached_value: &mut Value, new_res: &LookupResult) {
     let merge_field = |arr: &mut Value, val: &Value| {
-        if !val.is_null() {
-            if let Some(a) = arr.as_array_mut() {
-                a.push(val.clone());
-            }
+        if !val.is_null() && let Some(a) = arr.as_array_mut() {
+            a.push(val.clone());
         }
     };
 
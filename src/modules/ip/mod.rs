@@ -1,3 +1,4 @@
 // src/modules/ip/mod.rs
 
+pub mod discovery;
 pub mod lookup;
\ No newline at end of file
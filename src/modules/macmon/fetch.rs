@@ -6,6 +6,7 @@ use std::{
     time::{Duration, Instant},
 };
 
+use crate::core::shutdown;
 use lazy_static::lazy_static;
 use serde_json::Value;
 use tokio::{
@@ -46,7 +47,7 @@ pub async fn fetch_macmon() -> Option<Value> {
 
                 {
                     let last = last_access_clone.lock().unwrap();
-                    if last.elapsed() > Duration::from_secs(60) {
+                    if last.elapsed() > Duration::from_secs(60) || shutdown::is_shutting_down() {
                         *cache_clone.lock().unwrap() = None;
                         break;
                     }
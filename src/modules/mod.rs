@@ -3,6 +3,7 @@
 pub mod axum;
 pub mod router;
 pub mod app;
+pub mod auth;
 pub mod monitor;
 pub mod system;
 pub mod ip;
@@ -10,6 +11,7 @@ pub mod iostat;
 pub mod ram;
 pub mod cpu;
 pub mod docker;
+pub mod security;
 
 #[cfg(target_os = "macos")]
 pub mod macmon;
@@ -1,6 +1,6 @@
 /* src/modules/monitor/cpu.rs */
 
-use crate::core::response;
+use crate::core::{response, shutdown};
 use axum::response::Response;
 use chrono::{DateTime, Duration, Utc};
 use serde::Serialize;
@@ -137,10 +137,8 @@ mod platform {
 #[cfg(target_os = "linux")]
 mod platform {
     use super::*;
-    use linux_sysinfo::get_cpu_usage_json;
     use num_cpus;
     use regex::Regex;
-    use serde::Deserialize;
     use std::fs;
     use std::process::Command;
 
@@ -166,20 +164,20 @@ mod platform {
     }
 
     pub async fn fetch_dynamic_info() -> Option<CpuDynamicData> {
-        #[derive(Deserialize)]
-        struct LinuxCoreUsage {
-            core: usize,
-            usage: f32,
-        }
-
+        // Usage is measured between two reads of /proc/stat, at least the minimum interval apart.
         let usage_data_future = tokio::task::spawn_blocking(move || {
-            let usage_json = get_cpu_usage_json().ok()?;
-            let per_core_usage: Vec<LinuxCoreUsage> = serde_json::from_str(&usage_json).ok()?;
-            if per_core_usage.is_empty() { return None; }
-            let total_usage: f32 = per_core_usage.iter().map(|c| c.usage).sum();
-            let cores = per_core_usage.len();
-            let global_usage = if cores > 0 { total_usage / cores as f32 } else { 0.0 };
-            let per_core = per_core_usage.into_iter().map(|c| CoreUsage { core: c.core.to_string(), usage: c.usage }).collect();
+            let mut system =
+                System::new_with_specifics(RefreshKind::new().with_cpu(CpuRefreshKind::new().with_cpu_usage()));
+            std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+            system.refresh_cpu_usage();
+            let per_core: Vec<CoreUsage> = system
+                .cpus()
+                .iter()
+                .enumerate()
+                .map(|(i, cpu)| CoreUsage { core: i.to_string(), usage: cpu.cpu_usage() })
+                .collect();
+            if per_core.is_empty() { return None; }
+            let global_usage = per_core.iter().map(|c| c.usage).sum::<f32>() / per_core.len() as f32;
             Some((global_usage, per_core))
         });
 
@@ -248,8 +246,10 @@ impl CpuMonitor {
                     cache_guard.last_api_call
                 };
 
-                if Utc::now().signed_duration_since(last_call) > Duration::seconds(60) {
-                    // No API calls for 1 minute, exiting task.
+                if Utc::now().signed_duration_since(last_call) > Duration::seconds(60)
+                    || shutdown::is_shutting_down()
+                {
+                    // No API calls for 1 minute or the server is stopping, exiting task.
                     break;
                 }
 
@@ -287,6 +287,13 @@ impl CpuMonitor {
     }
 }
 
+// Aborts the background update task, if one is running.
+pub fn stop() {
+    if let Some(handle) = MONITOR.get().and_then(|m| m.task_handle.lock().unwrap().take()) {
+        handle.abort();
+    }
+}
+
 // --- API Handler ---
 
 pub async fn get_cpu_handler() -> Response {
@@ -1,6 +1,6 @@
 /* src/modules/monitor/network.rs */
 
-use crate::core::response;
+use crate::core::{response, shutdown};
 use axum::response::Response;
 use serde::Serialize;
 use serde_json::json;
@@ -75,8 +75,10 @@ mod platform {
                 // Spawn a background thread to collect data periodically.
                 thread::spawn(move || {
                     loop {
-                        // Check for inactivity timeout.
-                        if last_access.lock().unwrap().elapsed() > Duration::from_secs(60) {
+                        // Check for inactivity timeout or server shutdown.
+                        if last_access.lock().unwrap().elapsed() > Duration::from_secs(60)
+                            || shutdown::is_shutting_down()
+                        {
                             *cache.lock().unwrap() = None;
                             *running_flag.lock().unwrap() = false;
                             break;
@@ -165,11 +167,11 @@ mod platform {
                     continue;
                 }
 
-                if let (Some(rx_bytes_str), Some(tx_bytes_str)) = (parts.next(), parts.nth(7)) {
-                    if let (Ok(rx), Ok(tx)) = (rx_bytes_str.parse::<u64>(), tx_bytes_str.parse::<u64>()) {
-                        total_rx += rx;
-                        total_tx += tx;
-                    }
+                if let (Some(rx_bytes_str), Some(tx_bytes_str)) = (parts.next(), parts.nth(7))
+                    && let (Ok(rx), Ok(tx)) = (rx_bytes_str.parse::<u64>(), tx_bytes_str.parse::<u64>())
+                {
+                    total_rx += rx;
+                    total_tx += tx;
                 }
             }
         }
@@ -219,7 +221,7 @@ mod platform {
                     loop {
                         thread::sleep(Duration::from_secs(1));
                         let last = *last_access.lock().unwrap();
-                        if last.elapsed() > Duration::from_secs(60) {
+                        if last.elapsed() > Duration::from_secs(60) || shutdown::is_shutting_down() {
                             *cache.lock().unwrap() = None;
                             *running_flag.lock().unwrap() = false;
                             break;
@@ -77,23 +77,21 @@ fn parse_linux_ram_spec() -> Result<RamSpec, String> {
                     in_device_block = false;
                 }
 
-                if in_device_block {
-                    if let Some((key, value)) = trimmed_line.split_once(':') {
-                        let value = value.trim();
-                        // Ignore fields with default/empty values.
-                        if value == "Not Specified" || value == "Unknown" {
-                            continue;
+                if in_device_block && let Some((key, value)) = trimmed_line.split_once(':') {
+                    let value = value.trim();
+                    // Ignore fields with default/empty values.
+                    if value == "Not Specified" || value == "Unknown" {
+                        continue;
+                    }
+                    match key.trim() {
+                        "Size" if spec.capacity.is_empty() && value != "No Module Installed" => {
+                            spec.capacity = value.to_string()
                         }
-                        match key.trim() {
-                            "Size" if spec.capacity.is_empty() && value != "No Module Installed" => {
-                                spec.capacity = value.to_string()
-                            }
-                            "Type" if spec.ram_type.is_empty() => spec.ram_type = value.to_string(),
-                            "Manufacturer" if spec.manufacturer.is_empty() => {
-                                spec.manufacturer = value.to_string()
-                            }
-                            _ => {}
+                        "Type" if spec.ram_type.is_empty() => spec.ram_type = value.to_string(),
+                        "Manufacturer" if spec.manufacturer.is_empty() => {
+                            spec.manufacturer = value.to_string()
                         }
+                        _ => {}
                     }
                 }
             }
@@ -2,31 +2,78 @@
 
 use crate::core::response;
 use crate::middlewares;
-use crate::modules::{app, monitor, system, ip, ram, cpu, docker};
+use crate::middlewares::identity::Scope;
+use crate::middlewares::{audit, degraded, scope};
+use crate::modules::{app, auth, monitor, system, ip, ram, cpu, docker, security};
 use axum::{
+    middleware,
     response::Response,
     routing::{delete, get, post},
     Router,
 };
 
+// Public router served on TCP listeners, behind the full middleware stack.
 pub fn app_router() -> Router {
-    let router = Router::new()
+    middlewares::middleware::stack(routes())
+}
+
+// Router served on the local Unix socket, authenticated by peer credentials.
+pub fn local_router() -> Router {
+    middlewares::middleware::local_stack(routes())
+}
+
+// Each group is limited to the scope its routes need; `/`, `/v1/health` and `/v1/time` are public.
+fn routes() -> Router {
+    Router::new()
         .route("/", get(app::root::get_root_handler))
+        .route("/v1/health", get(app::health::get_health_handler))
+        .route("/v1/time", get(app::time::get_time_handler))
+        .merge(scoped(Scope::MonitorRead, monitor_routes()))
+        .merge(scoped(Scope::ContainersRead, container_read_routes()))
+        .merge(audited(scoped(Scope::ContainersWrite, container_write_routes())))
+        .merge(audited(scoped(Scope::SystemAdmin, admin_routes())))
+        .fallback(handler_404)
+}
+
+// The scope is checked before the collector, so callers without access learn nothing about the host.
+fn scoped(scope: Scope, router: Router) -> Router {
+    router
+        .route_layer(middleware::from_fn(degraded::handler))
+        .route_layer(middleware::from_fn_with_state(scope, scope::handler))
+}
+
+// Outermost, so calls rejected by the scope check are recorded too.
+fn audited(router: Router) -> Router {
+    router.route_layer(middleware::from_fn(audit::handler))
+}
+
+fn monitor_routes() -> Router {
+    Router::new()
+        .route("/v1/capabilities", get(app::capabilities::get_capabilities_handler))
         .route("/v1/ip", get(ip::lookup::get_ip_handler))
         .route("/v2/ip", get(ip::lookup::get_geoip_handler))
         .route("/v1/system/information",get(system::info::get_sysinfo_handler),)
         .route("/v1/system/ipconfig", get(system::ipconfig::get_ipconfig_handler))
+        .route("/v1/system/dependencies", get(system::dependencies::get_dependencies_handler))
         .route("/v1/monitor/cpu", get(monitor::cpu::get_cpu_handler))
         .route("/v1/monitor/cpu/power", get(cpu::power::get_cpu_power_handler))
         .route("/v1/monitor/memory", get(monitor::memory::get_memory_handler))
         .route("/v1/monitor/storage", get(monitor::storage::get_storage_handler))
         .route("/v1/monitor/network", get(monitor::network::get_network_handler))
         .route("/v1/spec/ram", get(ram::spec::get_ram_spec_handler))
+}
+
+fn container_read_routes() -> Router {
+    Router::new()
         .route("/v1/containers", get(docker::ps::get_docker_ps_handler))
         .route("/v1/containers/version", get(docker::versions::get_docker_version_handler))
         .route("/v1/containers/daemon/version", get(docker::versions::get_daemon_version_handler))
         .route("/v1/containers/info/{id}", get(docker::containers::get_container_handler))
         .route("/v1/containers/{id}/info", get(docker::containers::get_container_handler))
+}
+
+fn container_write_routes() -> Router {
+    Router::new()
         .route("/v1/containers/{id}/start", post(docker::operations::post_start_container_handler))
         .route("/v1/containers/{id}/stop", post(docker::operations::post_stop_container_handler))
         .route("/v1/containers/{id}/pause", post(docker::operations::post_pause_container_handler))
@@ -34,8 +81,19 @@ pub fn app_router() -> Router {
         .route("/v1/containers/{id}/restart", post(docker::operations::post_restart_container_handler))
         .route("/v1/containers/{id}/kill", post(docker::operations::post_kill_container_handler))
         .route("/v1/containers/{id}", delete(docker::operations::delete_container_handler))
-        .fallback(handler_404);
-    middlewares::middleware::stack(router)
+}
+
+fn admin_routes() -> Router {
+    Router::new()
+        .route("/v1/auth/rotate", post(auth::rotate::post_rotate_handler))
+        .route("/v1/auth/keys", get(auth::keys::get_keys_handler).post(auth::keys::post_key_handler))
+        .route("/v1/auth/keys/{id}", delete(auth::keys::delete_key_handler))
+        .route("/v1/audit", get(system::audit::get_audit_handler))
+        .route("/v1/security/bans", get(security::bans::get_bans_handler).post(security::bans::post_ban_handler))
+        .route("/v1/security/bans/{id}", delete(security::bans::delete_ban_handler))
+        .route("/v1/security/acl", get(security::acl::get_acl_handler))
+        .route("/v1/security/traps", get(security::traps::get_traps_handler))
+        .route_layer(middleware::from_fn(scope::current_key_only))
 }
 
 async fn handler_404() -> Response {
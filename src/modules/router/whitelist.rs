@@ -3,3 +3,7 @@
 // This module provides a simple, constant list of paths that can bypass
 // certain security checks like the blacklist and guard.
 pub const WHITELISTED_PATHS: &[&str] = &["/"];
+
+// Paths answered without a token, for probes that cannot hold credentials.
+// `/v1/time` lets clients check their clock before they can produce a valid token.
+pub const PUBLIC_PATHS: &[&str] = &["/", "/v1/health", "/v1/time"];
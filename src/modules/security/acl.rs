@@ -0,0 +1,10 @@
+// src/modules/security/acl.rs
+
+use crate::core::response;
+use crate::middlewares::acl;
+use axum::response::Response;
+
+// The network ACL as configured, with hit counts since startup.
+pub async fn get_acl_handler() -> Response {
+    response::success(Some(acl::stats()))
+}
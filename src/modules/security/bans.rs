@@ -0,0 +1,63 @@
+// src/modules/security/bans.rs
+
+use crate::common::bans::{self, RemoveError};
+use crate::core::response;
+use crate::middlewares::audit;
+use crate::middlewares::identity::Identity;
+use axum::{
+    body::Bytes,
+    extract::Path,
+    http::StatusCode,
+    response::Response,
+    Extension,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+#[derive(Deserialize)]
+struct BanRequest {
+    // An address or CIDR block.
+    network: String,
+    reason: Option<String>,
+    // Seconds; omitted or 0 bans permanently.
+    duration: Option<u64>,
+}
+
+// Active bans, and the addresses collecting points towards one.
+pub async fn get_bans_handler() -> Response {
+    response::success(Some(json!({
+        "bans": bans::list(),
+        "scores": bans::scores(),
+    })))
+}
+
+pub async fn post_ban_handler(identity: Option<Extension<Identity>>, body: Bytes) -> Response {
+    let request = match serde_json::from_slice::<BanRequest>(&body) {
+        Ok(request) => request,
+        Err(e) => return response::error(StatusCode::BAD_REQUEST, format!("Invalid body: {}", e)),
+    };
+    let network = match bans::parse_network(&request.network) {
+        Ok(network) => network,
+        Err(e) => return response::error(StatusCode::BAD_REQUEST, e),
+    };
+    let source = identity.map_or_else(|| "admin".to_string(), |Extension(identity)| identity.to_string());
+    let reason = request.reason.unwrap_or_else(|| "manual".to_string());
+    let duration = request.duration.filter(|secs| *secs > 0).map(Duration::from_secs);
+
+    let res = match bans::add(network, &reason, &source, duration) {
+        Ok(ban) => response::success(Some(json!({ "ban": ban }))),
+        Err(e) => response::error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+    audit::annotate(res, &network.to_string(), None)
+}
+
+// Lifts a ban by id or network; a CIDR block's '/' is sent as %2F.
+pub async fn delete_ban_handler(Path(id): Path<String>) -> Response {
+    let res = match bans::remove(&id) {
+        Ok(ban) => response::success(Some(json!({ "lifted": ban }))),
+        Err(RemoveError::NotFound(e)) => response::error(StatusCode::NOT_FOUND, e),
+        Err(RemoveError::Io(e)) => response::error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+    audit::annotate(res, &id, None)
+}
@@ -0,0 +1,5 @@
+// src/modules/security/mod.rs
+
+pub mod acl;
+pub mod bans;
+pub mod traps;
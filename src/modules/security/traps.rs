@@ -0,0 +1,10 @@
+// src/modules/security/traps.rs
+
+use crate::common::traps;
+use crate::core::response;
+use axum::response::Response;
+
+// Trap rules in effect, with hits per rule, per address and per day.
+pub async fn get_traps_handler() -> Response {
+    response::success(Some(traps::stats()))
+}
@@ -0,0 +1,75 @@
+/* src/modules/system/audit.rs */
+
+use crate::common::audit;
+use crate::core::response;
+use axum::{extract::Query, http::StatusCode, response::Response};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    // RFC 3339 or unix seconds, inclusive.
+    from: Option<String>,
+    to: Option<String>,
+    // Matches the identity as recorded, e.g. `apikey:deploy`, or just its name.
+    actor: Option<String>,
+    limit: Option<usize>,
+}
+
+// Returns the newest matching entries, oldest first, and whether the hash chain
+// over the whole file is intact.
+pub async fn get_audit_handler(Query(query): Query<AuditQuery>) -> Response {
+    let from = match query.from.as_deref().map(parse_time).transpose() {
+        Ok(from) => from,
+        Err(e) => return response::error(StatusCode::BAD_REQUEST, e),
+    };
+    let to = match query.to.as_deref().map(parse_time).transpose() {
+        Ok(to) => to,
+        Err(e) => return response::error(StatusCode::BAD_REQUEST, e),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let (entries, broken_at) = match tokio::task::spawn_blocking(audit::read).await {
+        Ok(result) => result,
+        Err(_) => return response::internal_error(),
+    };
+    let total = entries.len();
+    let mut matching: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| {
+            let time = DateTime::parse_from_rfc3339(&entry.time).map(|t| t.with_timezone(&Utc));
+            let in_range = match time {
+                Ok(time) => from.is_none_or(|from| time >= from) && to.is_none_or(|to| time <= to),
+                Err(_) => from.is_none() && to.is_none(),
+            };
+            let by_actor = query.actor.as_deref().is_none_or(|actor| {
+                entry.actor == actor || entry.actor.split_once(':').is_some_and(|(_, name)| name == actor)
+            });
+            in_range && by_actor
+        })
+        .collect();
+    let skip = matching.len().saturating_sub(limit);
+    matching.drain(..skip);
+
+    response::success(Some(json!({
+        "entries": matching,
+        "chain": {
+            "valid": broken_at.is_none(),
+            "broken_at": broken_at,
+            "total": total,
+        },
+    })))
+}
+
+fn parse_time(raw: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(secs) = raw.parse::<i64>() {
+        return DateTime::from_timestamp(secs, 0).ok_or_else(|| format!("Invalid time '{}'", raw));
+    }
+    DateTime::parse_from_rfc3339(raw)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| format!("Invalid time '{}', expected RFC 3339 or unix seconds", raw))
+}
@@ -0,0 +1,51 @@
+/* src/modules/system/dependencies.rs */
+
+use crate::common::privilege;
+use crate::core::{collector, requirement, response};
+use axum::response::Response;
+use serde_json::json;
+
+// Reports every collector with what it lacks, and every external command with
+// where it was found and which collectors use it.
+pub async fn get_dependencies_handler() -> Response {
+    let collectors: Vec<_> = collector::COLLECTORS
+        .iter()
+        .map(|c| {
+            let missing = c.missing();
+            json!({
+                "name": c.name,
+                "routes": c.routes,
+                "available": missing.is_empty(),
+                "missing": missing,
+            })
+        })
+        .collect();
+
+    let commands: Vec<_> = collector::required_commands()
+        .into_iter()
+        .map(|cmd| {
+            let path = requirement::locate(cmd);
+            json!({
+                "name": cmd,
+                "installed": path.is_some(),
+                "path": path.map(|p| p.display().to_string()),
+                "used_by": collector::users_of(cmd),
+            })
+        })
+        .collect();
+
+    let report = requirement::check_dependencies();
+    let install = if report.missing.is_empty() {
+        None
+    } else {
+        requirement::install_command(&report)
+    };
+
+    response::success(Some(json!({
+        "os": report.os,
+        "running_as": privilege::describe(),
+        "collectors": collectors,
+        "commands": commands,
+        "install": install,
+    })))
+}
@@ -1,5 +1,6 @@
 /* src/modules/system/info.rs */
 
+use crate::common::env;
 use crate::core::response;
 use axum::response::Response;
 use chrono::{DateTime, Duration, Utc};
@@ -20,7 +21,8 @@ struct SystemInfoCache {
 
 impl SystemInfoCache {
     fn is_expired(&self) -> bool {
-        Utc::now().signed_duration_since(self.cached_at) > Duration::minutes(15)
+        let ttl = Duration::from_std(env::current().sysinfo_cache_ttl).unwrap_or_default();
+        Utc::now().signed_duration_since(self.cached_at) > ttl
     }
 }
 
@@ -106,25 +108,17 @@ fn format_uptime_short(uptime_secs: u64) -> String {
     if years > 0 {
         parts.push(format!("{}y", years));
     }
-    if months > 0 || !parts.is_empty() {
-        if months > 0 {
-            parts.push(format!("{}mo", months));
-        }
+    if months > 0 {
+        parts.push(format!("{}mo", months));
     }
-    if days > 0 || !parts.is_empty() {
-        if days > 0 {
-            parts.push(format!("{}d", days));
-        }
+    if days > 0 {
+        parts.push(format!("{}d", days));
     }
-    if hours > 0 || !parts.is_empty() {
-        if hours > 0 {
-            parts.push(format!("{}h", hours));
-        }
+    if hours > 0 {
+        parts.push(format!("{}h", hours));
     }
-    if minutes > 0 || !parts.is_empty() {
-        if minutes > 0 {
-            parts.push(format!("{}m", minutes));
-        }
+    if minutes > 0 {
+        parts.push(format!("{}m", minutes));
     }
     parts.push(format!("{}s", seconds));
 
@@ -135,10 +129,10 @@ fn get_cached_system_info() -> SystemInfoCache {
     let cache = CACHE.get_or_init(|| Arc::new(Mutex::new(None)));
     let mut cache_guard = cache.lock().unwrap();
 
-    if let Some(ref cached_info) = *cache_guard {
-        if !cached_info.is_expired() {
-            return cached_info.clone();
-        }
+    if let Some(ref cached_info) = *cache_guard
+        && !cached_info.is_expired()
+    {
+        return cached_info.clone();
     }
 
     let (ipv4, ipv6) = get_ip_addresses();
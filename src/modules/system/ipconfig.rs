@@ -3,7 +3,6 @@
 use axum::response::IntoResponse;
 use serde::Serialize;
 use std::process::Command;
-use serde_json::json;
 
 #[cfg(target_os = "macos")]
 use std::collections::HashMap;
@@ -227,7 +226,7 @@ fn parse_linux_ip_a(text: &str) -> Vec<IpConfig> {
                 current.device_name = iface.to_string();
                 current.status = if line.contains("UP") { "active" } else { "inactive" }.to_string();
                 if let Some(mtu) = line.split("mtu").nth(1) {
-                    current.mtu = mtu.trim().split_whitespace().next().and_then(|v| v.parse().ok());
+                    current.mtu = mtu.split_whitespace().next().and_then(|v| v.parse().ok());
                 }
 
                 if iface.contains("docker") {
@@ -240,17 +239,15 @@ fn parse_linux_ip_a(text: &str) -> Vec<IpConfig> {
             }
         } else {
             if line.contains("link/ether") {
-                if let Some(mac) = line.trim().split_whitespace().nth(1) {
+                if let Some(mac) = line.split_whitespace().nth(1) {
                     current.mac_address = mac.to_string();
                 }
             } else if line.contains("inet ") {
-                if let Some(ip) = line.trim().split_whitespace().nth(1) {
+                if let Some(ip) = line.split_whitespace().nth(1) {
                     current.ip.ipv4.push(ip.split('/').next().unwrap_or("").to_string());
                 }
-            } else if line.contains("inet6 ") {
-                if let Some(ip) = line.trim().split_whitespace().nth(1) {
-                    current.ip.ipv6.push(ip.split('/').next().unwrap_or("").to_string());
-                }
+            } else if line.contains("inet6 ") && let Some(ip) = line.split_whitespace().nth(1) {
+                current.ip.ipv6.push(ip.split('/').next().unwrap_or("").to_string());
             }
         }
     }
@@ -1,4 +1,6 @@
 /* src/modules/system/mod.rs */
 
 pub mod info;
-pub mod ipconfig;
\ No newline at end of file
+pub mod ipconfig;
+pub mod dependencies;
+pub mod audit;